@@ -0,0 +1,53 @@
+use crate::prelude::*;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const EMPTY: Self = Self {
+        x: Interval::EMPTY,
+        y: Interval::EMPTY,
+        z: Interval::EMPTY,
+    };
+
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Box with `a` and `b` as opposite corners.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        let axis = |n: usize| Interval::new(f64::min(a[n], b[n]), f64::max(a[n], b[n]));
+
+        Self::new(axis(0), axis(1), axis(2))
+    }
+
+    /// Smallest box containing both `box0` and `box1`.
+    pub fn surrounding(box0: &Aabb, box1: &Aabb) -> Self {
+        Self::new(
+            Interval::surrounding(&box0.x, &box1.x),
+            Interval::surrounding(&box0.y, &box1.y),
+            Interval::surrounding(&box0.z, &box1.z),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.size() < 0.0 || self.y.size() < 0.0 || self.z.size() < 0.0
+    }
+
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min, self.y.min, self.z.min)
+    }
+
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max, self.y.max, self.z.max)
+    }
+
+    pub fn center(&self) -> Point3 {
+        0.5 * (self.min() + self.max())
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+/// Settings for adaptive sampling.
+///
+/// Pixels are sampled in passes of `batch_size` samples. After `min_samples`, a pixel stops
+/// receiving samples once its estimated error drops below `threshold`, and the samples it did
+/// not use are spent on the pixels that are still noisy.
+#[derive(Debug, Clone)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel takes before its error is first estimated
+    pub min_samples: i32,
+    /// Hard cap on samples for any single pixel
+    pub max_samples: i32,
+    /// Standard error of the gamma-encoded pixel luminance at which a pixel is converged
+    pub threshold: f64,
+    /// Samples added to each unconverged pixel per pass
+    pub batch_size: i32,
+    /// Optional path of a grayscale image of the per-pixel sample counts
+    pub sample_count_map: Option<PathBuf>,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            min_samples: 16,
+            max_samples: 1024,
+            threshold: 0.01,
+            batch_size: 8,
+            sample_count_map: None,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    pub fn with_min_samples(mut self, min_samples: i32) -> Self {
+        self.min_samples = min_samples;
+
+        self
+    }
+
+    pub fn with_max_samples(mut self, max_samples: i32) -> Self {
+        self.max_samples = max_samples;
+
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i32) -> Self {
+        self.batch_size = batch_size;
+
+        self
+    }
+
+    pub fn with_sample_count_map(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sample_count_map = Some(path.into());
+
+        self
+    }
+
+    /// Checks that the settings describe a render that terminates.
+    pub fn validate(&self) -> std::io::Result<()> {
+        let invalid = |message| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                message,
+            ))
+        };
+        if self.batch_size < 1 {
+            return invalid("adaptive sampling needs a batch size of at least 1");
+        }
+        if self.min_samples > self.max_samples {
+            return invalid("adaptive sampling needs min_samples to be at most max_samples");
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether a pixel with `samples` samples and estimated `error` needs more samples.
+    pub fn needs_samples(&self, samples: i32, error: f64) -> bool {
+        samples < self.min_samples || (samples < self.max_samples && error >= self.threshold)
+    }
+}
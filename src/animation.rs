@@ -0,0 +1,266 @@
+use std::{
+    ops::{Add, Mul, Sub},
+    path::{Path, PathBuf},
+};
+
+use crate::{camera::Camera, prelude::*};
+
+/// How animated values change between keyframes.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Interpolation {
+    /// Straight lines between keys, changing speed abruptly at each key
+    #[default]
+    Linear,
+    /// Smooth Catmull-Rom spline through the keys
+    CatmullRom,
+}
+
+/// Keyframes of one animated value, ordered by frame.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keys: Vec<(f64, T)>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self { keys: Vec::new() }
+    }
+}
+
+impl<T> Track<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+{
+    /// Sets the value at `frame`, replacing any key already there.
+    pub fn insert(&mut self, frame: f64, value: T) {
+        let index = self
+            .keys
+            .partition_point(|&(key_frame, _)| key_frame < frame);
+        match self.keys.get(index) {
+            Some(&(key_frame, _)) if key_frame == frame => self.keys[index] = (frame, value),
+            _ => self.keys.insert(index, (frame, value)),
+        }
+    }
+
+    /// Returns the value at `frame`, held constant before the first and after the last key,
+    /// or `None` if the track has no keys.
+    pub fn value_at(&self, frame: f64, interpolation: Interpolation) -> Option<T> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        if frame <= first.0 {
+            return Some(first.1);
+        }
+        if frame >= last.0 {
+            return Some(last.1);
+        }
+
+        let next = self
+            .keys
+            .partition_point(|&(key_frame, _)| key_frame <= frame);
+        let (f1, p1) = self.keys[next - 1];
+        let (f2, p2) = self.keys[next];
+        let t = (frame - f1) / (f2 - f1);
+
+        match interpolation {
+            Interpolation::Linear => Some(p1 + (p2 - p1) * t),
+            Interpolation::CatmullRom => {
+                // Hermite segment with tangents from the neighboring keys, scaled to the
+                // segment length so unevenly spaced keys keep a smooth speed.
+                let tangent = |index: usize| {
+                    let before = self.keys[index.saturating_sub(1)];
+                    let after = self.keys[usize::min(index + 1, self.keys.len() - 1)];
+                    (after.1 - before.1) * ((f2 - f1) / (after.0 - before.0))
+                };
+                let (m1, m2) = (tangent(next - 1), tangent(next));
+
+                let t2 = t * t;
+                let t3 = t2 * t;
+                Some(
+                    p1 * (2.0 * t3 - 3.0 * t2 + 1.0)
+                        + m1 * (t3 - 2.0 * t2 + t)
+                        + p2 * (-2.0 * t3 + 3.0 * t2)
+                        + m2 * (t3 - t2),
+                )
+            }
+        }
+    }
+}
+
+/// Keyframed camera parameters. Parameters without keys keep the camera's own setting.
+#[derive(Debug, Default, Clone)]
+pub struct CameraAnimation {
+    pub interpolation: Interpolation,
+    pub lookfrom: Track<Point3>,
+    pub lookat: Track<Point3>,
+    pub vup: Track<Vec3>,
+    pub vfov: Track<f64>,
+    pub focus_dist: Track<f64>,
+    pub defocus_angle: Track<f64>,
+}
+
+impl CameraAnimation {
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+
+        self
+    }
+
+    pub fn with_lookfrom_key(mut self, frame: f64, lookfrom: Point3) -> Self {
+        self.lookfrom.insert(frame, lookfrom);
+
+        self
+    }
+
+    pub fn with_lookat_key(mut self, frame: f64, lookat: Point3) -> Self {
+        self.lookat.insert(frame, lookat);
+
+        self
+    }
+
+    pub fn with_vup_key(mut self, frame: f64, vup: Vec3) -> Self {
+        self.vup.insert(frame, vup);
+
+        self
+    }
+
+    pub fn with_vfov_key(mut self, frame: f64, vfov: f64) -> Self {
+        self.vfov.insert(frame, vfov);
+
+        self
+    }
+
+    pub fn with_focus_dist_key(mut self, frame: f64, focus_dist: f64) -> Self {
+        self.focus_dist.insert(frame, focus_dist);
+
+        self
+    }
+
+    pub fn with_defocus_angle_key(mut self, frame: f64, defocus_angle: f64) -> Self {
+        self.defocus_angle.insert(frame, defocus_angle);
+
+        self
+    }
+
+    /// Sets the animated parameters of `camera` to their values at `frame`.
+    pub fn apply(&self, camera: &mut Camera, frame: f64) {
+        let interpolation = self.interpolation;
+        if let Some(lookfrom) = self.lookfrom.value_at(frame, interpolation) {
+            camera.lookfrom = lookfrom;
+        }
+        if let Some(lookat) = self.lookat.value_at(frame, interpolation) {
+            camera.lookat = lookat;
+        }
+        if let Some(vup) = self.vup.value_at(frame, interpolation) {
+            camera.vup = vup;
+        }
+        if let Some(vfov) = self.vfov.value_at(frame, interpolation) {
+            camera.vfov = vfov;
+        }
+        if let Some(focus_dist) = self.focus_dist.value_at(frame, interpolation) {
+            camera.focus_dist = focus_dist;
+        }
+        if let Some(defocus_angle) = self.defocus_angle.value_at(frame, interpolation) {
+            camera.defocus_angle = defocus_angle;
+        }
+    }
+}
+
+/// Range of frames of a shot to render as numbered images. Scene time runs from 0 at the
+/// first frame of the shot to 1 at its last, the interval over which moving objects are
+/// defined, whichever of its frames are rendered.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    /// Output file of each frame, where a run of `#` is replaced by the zero-padded frame
+    /// number, e.g. `frames/frame_####.ppm`
+    pub output: PathBuf,
+    /// First frame of the shot
+    pub shot_start: i32,
+    /// Last frame of the shot
+    pub shot_end: i32,
+    /// First frame to render
+    pub start: i32,
+    /// Last frame to render, included in the sequence
+    pub end: i32,
+    /// Distance between rendered frames
+    pub step: i32,
+    /// Frames per second, which sets how much scene time the shutter of a frame spans
+    pub fps: f64,
+}
+
+impl Sequence {
+    /// Creates a sequence rendering every frame of the shot from `shot_start` to `shot_end`.
+    pub fn new(output: impl Into<PathBuf>, shot_start: i32, shot_end: i32) -> Self {
+        Self {
+            output: output.into(),
+            shot_start,
+            shot_end,
+            start: shot_start,
+            end: shot_end,
+            step: 1,
+            fps: 24.0,
+        }
+    }
+
+    /// Renders only the frames from `start` to `end` of the shot, keeping their scene times.
+    pub fn with_frames(mut self, start: i32, end: i32) -> Self {
+        self.start = start;
+        self.end = end;
+
+        self
+    }
+
+    pub fn with_step(mut self, step: i32) -> Self {
+        self.step = step;
+
+        self
+    }
+
+    pub fn with_fps(mut self, fps: f64) -> Self {
+        self.fps = fps;
+
+        self
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = i32> {
+        (self.start..=self.end).step_by(self.step.max(1) as usize)
+    }
+
+    /// Scene time at which the shutter of `frame` opens.
+    pub fn frame_time(&self, frame: i32) -> f64 {
+        (frame - self.shot_start) as f64 / self.frame_span()
+    }
+
+    /// Scene time that passes per second of shutter time.
+    pub fn time_scale(&self) -> f64 {
+        self.fps / self.frame_span()
+    }
+
+    /// Number of frame intervals the shot spans, at least 1.
+    fn frame_span(&self) -> f64 {
+        (self.shot_end - self.shot_start).max(1) as f64
+    }
+}
+
+/// Returns `path` numbered for `frame`: its first run of `#` replaced by the zero-padded frame
+/// number, or without one the frame number appended to the file stem, e.g. `render_0012.ckpt`.
+pub fn frame_path(path: &Path, frame: i32) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let file_name = match file_name.find('#') {
+        Some(start) => {
+            let width = file_name[start..].chars().take_while(|&c| c == '#').count();
+            format!(
+                "{}{frame:0width$}{}",
+                &file_name[..start],
+                &file_name[start + width..]
+            )
+        }
+        None => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            match path.extension() {
+                Some(extension) => format!("{stem}_{frame:04}.{}", extension.to_string_lossy()),
+                None => format!("{stem}_{frame:04}"),
+            }
+        }
+    };
+
+    path.with_file_name(file_name)
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::{
+    checkpoint::{debug_hash, read_f64, read_i32, write_f64, write_i32},
+    hittable::HitRecord,
+    prelude::*,
+};
+
+/// Arbitrary output variable: an auxiliary per-pixel value derived from the first hit of each
+/// camera ray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Distance from the camera along its view direction
+    Depth,
+    /// Shading normal in world space
+    Normal,
+    /// Albedo of the first surface hit
+    Albedo,
+    /// World-space position of the first hit
+    Position,
+    /// Hash of the material at the first hit, so equal materials share an identifier (0 is the
+    /// background)
+    MaterialId,
+    /// Index of the top-level object at the first hit, starting from 1 (0 is the background)
+    ObjectId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::Position,
+        Aov::MaterialId,
+        Aov::ObjectId,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Position => "position",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+        }
+    }
+}
+
+/// Per-pixel accumulation of every AOV. Depth, normal and position are averaged over the
+/// samples of a pixel that hit a surface, so silhouettes keep the values of the surface. Albedo
+/// is averaged over all samples, with misses counting as black, to match the beauty image the
+/// denoiser divides by it. Identifiers are taken from the first sample that hits.
+#[derive(Debug, Clone)]
+pub struct AovBuffers {
+    pub width: i32,
+    pub height: i32,
+    samples: Vec<i32>,
+    hits: Vec<i32>,
+    depth: Vec<f64>,
+    normal: Vec<Vec3>,
+    albedo: Vec<Color>,
+    position: Vec<Point3>,
+    material_id: Vec<u32>,
+    object_id: Vec<u32>,
+    /// Identifier of every material seen so far, by the address of the material, so that each
+    /// is only hashed once
+    material_ids: HashMap<*const (), u32>,
+}
+
+impl AovBuffers {
+    pub fn new(width: i32, height: i32) -> Self {
+        let len = (width * height) as usize;
+        Self {
+            width,
+            height,
+            samples: vec![0; len],
+            hits: vec![0; len],
+            depth: vec![0.0; len],
+            normal: vec![Vec3::default(); len],
+            albedo: vec![Color::default(); len],
+            position: vec![Point3::default(); len],
+            material_id: vec![0; len],
+            object_id: vec![0; len],
+            material_ids: HashMap::new(),
+        }
+    }
+
+    /// Records the first hit of a camera ray through pixel `i`, `j` at camera-space `depth`,
+    /// or a miss if `rec` is `None`.
+    pub fn add_sample(&mut self, i: i32, j: i32, rec: Option<&HitRecord>, depth: f64) {
+        let index = self.index(i, j);
+        self.samples[index] += 1;
+
+        let Some(rec) = rec else {
+            return;
+        };
+
+        self.hits[index] += 1;
+        self.depth[index] += depth;
+        self.normal[index] += rec.normal;
+        self.albedo[index] += rec.mat.albedo(rec);
+        self.position[index] += rec.p;
+
+        if self.hits[index] == 1 {
+            // Equal materials hash alike, so they share an identifier between objects and runs.
+            // Keep identifiers within 24 bits so they are exact when stored as f32.
+            self.material_id[index] = *self
+                .material_ids
+                .entry(Rc::as_ptr(&rec.mat) as *const ())
+                .or_insert_with(|| (debug_hash(&rec.mat) as u32 & 0xff_ffff).max(1));
+            self.object_id[index] = rec.object_id;
+        }
+    }
+
+    /// Returns the value of `aov` at pixel `i`, `j`. Scalar outputs are repeated in all three
+    /// components.
+    pub fn value(&self, aov: Aov, i: i32, j: i32) -> Vec3 {
+        let index = self.index(i, j);
+        let scale = 1.0 / f64::max(1.0, self.samples[index] as f64);
+        let hit_scale = 1.0 / f64::max(1.0, self.hits[index] as f64);
+        let splat = |x: f64| Vec3::new(x, x, x);
+
+        match aov {
+            Aov::Depth => splat(hit_scale * self.depth[index]),
+            Aov::Normal => hit_scale * self.normal[index],
+            Aov::Albedo => scale * self.albedo[index],
+            Aov::Position => hit_scale * self.position[index],
+            Aov::MaterialId => splat(self.material_id[index] as f64),
+            Aov::ObjectId => splat(self.object_id[index] as f64),
+        }
+    }
+
+    /// Writes `aov` as a little-endian color PFM (portable float map).
+    pub fn write_pfm(&self, aov: Aov, mut out: impl std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "PF")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "-1.0")?;
+
+        // PFM scanlines are stored bottom to top.
+        for j in (0..self.height).rev() {
+            for i in 0..self.width {
+                for c in self.value(aov, i, j).e {
+                    out.write_all(&(c as f32).to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the raw buffers, for checkpointing.
+    pub(crate) fn write_state(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        for index in 0..self.samples.len() {
+            write_i32(out, self.samples[index])?;
+            write_i32(out, self.hits[index])?;
+            write_f64(out, self.depth[index])?;
+            for v in [self.normal[index], self.albedo[index], self.position[index]] {
+                for c in v.e {
+                    write_f64(out, c)?;
+                }
+            }
+            write_i32(out, self.material_id[index] as i32)?;
+            write_i32(out, self.object_id[index] as i32)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads buffers written by [`AovBuffers::write_state`].
+    pub(crate) fn read_state(
+        input: &mut impl std::io::Read,
+        width: i32,
+        height: i32,
+    ) -> std::io::Result<Self> {
+        let mut aovs = Self::new(width, height);
+        for index in 0..aovs.samples.len() {
+            aovs.samples[index] = read_i32(input)?;
+            aovs.hits[index] = read_i32(input)?;
+            aovs.depth[index] = read_f64(input)?;
+            for v in [
+                &mut aovs.normal[index],
+                &mut aovs.albedo[index],
+                &mut aovs.position[index],
+            ] {
+                for c in &mut v.e {
+                    *c = read_f64(input)?;
+                }
+            }
+            aovs.material_id[index] = read_i32(input)? as u32;
+            aovs.object_id[index] = read_i32(input)? as u32;
+        }
+
+        Ok(aovs)
+    }
+
+    fn index(&self, i: i32, j: i32) -> usize {
+        (j * self.width + i) as usize
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use crate::{image::Image, prelude::*};
+
+/// Outline of the lens opening, which out-of-focus highlights take the shape of.
+#[derive(Debug, Default, Clone)]
+pub enum ApertureShape {
+    /// Perfectly round opening
+    #[default]
+    Disk,
+    /// Regular polygon formed by `blades` straight diaphragm blades, turned by `rotation`
+    /// degrees
+    Polygon { blades: u32, rotation: f64 },
+    /// Opening given by an image, where brighter pixels let more light through
+    Mask(Rc<ApertureMask>),
+}
+
+/// Lens aperture used for depth of field.
+#[derive(Debug, Clone)]
+pub struct Aperture {
+    pub shape: ApertureShape,
+    /// Strength of optical vignetting, which clips the aperture towards the image corners into
+    /// a cat's-eye shape; 0 disables it
+    pub cat_eye: f64,
+    /// Anamorphic squeeze factor, stretching the aperture vertically; 1 for spherical lenses
+    pub anamorphic_squeeze: f64,
+}
+
+impl Default for Aperture {
+    fn default() -> Self {
+        Self {
+            shape: ApertureShape::Disk,
+            cat_eye: 0.0,
+            anamorphic_squeeze: 1.0,
+        }
+    }
+}
+
+impl Aperture {
+    pub fn with_shape(mut self, shape: ApertureShape) -> Self {
+        self.shape = shape;
+
+        self
+    }
+
+    pub fn with_cat_eye(mut self, cat_eye: f64) -> Self {
+        self.cat_eye = cat_eye;
+
+        self
+    }
+
+    pub fn with_anamorphic_squeeze(mut self, anamorphic_squeeze: f64) -> Self {
+        self.anamorphic_squeeze = anamorphic_squeeze;
+
+        self
+    }
+
+    /// Returns a random point on the aperture, within `[-1, 1]` in both coordinates (inside
+    /// the unit disk except for masks), for a ray through image position `x`, `y` (both in
+    /// `[-1, 1]`, y up). Returns `None` if the sampled point is
+    /// blocked by optical vignetting.
+    pub fn sample(&self, x: f64, y: f64) -> Option<Vec3> {
+        let p = match &self.shape {
+            ApertureShape::Disk => random_in_unit_disk(),
+            ApertureShape::Polygon { blades, rotation } => {
+                random_in_polygon(*blades, rotation.to_radians())
+            }
+            ApertureShape::Mask(mask) => mask.sample(),
+        };
+
+        // Off-axis rays are also clipped by the rim of the lens barrel, modeled as a second unit
+        // disk that moves away from the aperture towards the image corners.
+        if self.cat_eye > 0.0 {
+            let rim = Vec3::new(self.cat_eye * x, self.cat_eye * y, 0.0);
+            if (p + rim).length_squared() > 1.0 {
+                return None;
+            }
+        }
+
+        Some(Vec3::new(p.x() / self.anamorphic_squeeze, p.y(), 0.0))
+    }
+}
+
+/// Aperture shape loaded from an image, sampled by rejection.
+#[derive(Debug, Clone)]
+pub struct ApertureMask {
+    image: Image,
+    max_transmission: f64,
+}
+
+impl ApertureMask {
+    /// Loads the mask from a PPM whose square (or centered inscribed square) covers the
+    /// aperture.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let image = Image::read_ppm(std::fs::File::open(path)?)?;
+        Ok(Self::new(image))
+    }
+
+    pub fn new(image: Image) -> Self {
+        let max_transmission = image
+            .pixels
+            .iter()
+            .map(|&pixel| luminance(pixel))
+            .fold(0.0, f64::max);
+
+        Self {
+            image,
+            max_transmission,
+        }
+    }
+
+    /// Transmission at `p` in `[-1, 1]` in both coordinates, mapped onto the centered square
+    /// of the image.
+    fn transmission(&self, p: Vec3) -> f64 {
+        let size = i32::min(self.image.width, self.image.height);
+        let i = ((p.x() + 1.0) / 2.0 * size as f64) as i32 + (self.image.width - size) / 2;
+        let j = ((1.0 - p.y()) / 2.0 * size as f64) as i32 + (self.image.height - size) / 2;
+        let i = i.clamp(0, self.image.width - 1);
+        let j = j.clamp(0, self.image.height - 1);
+
+        luminance(self.image.get(i, j))
+    }
+
+    fn sample(&self) -> Vec3 {
+        if self.max_transmission <= 0.0 {
+            return Vec3::default();
+        }
+
+        // Rejection sampling; give up on nearly opaque masks rather than loop forever.
+        for _ in 0..1000 {
+            let p = Vec3::new(
+                rng::random_range(-1.0..1.0),
+                rng::random_range(-1.0..1.0),
+                0.0,
+            );
+            if rng::random::<f64>() * self.max_transmission < self.transmission(p) {
+                return p;
+            }
+        }
+
+        Vec3::default()
+    }
+}
+
+/// Returns a uniformly distributed point in the regular polygon with `blades` sides inscribed
+/// in the unit circle, with its first vertex at angle `rotation`.
+fn random_in_polygon(blades: u32, rotation: f64) -> Vec3 {
+    let blades = blades.max(3);
+    let wedge = 2.0 * PI / blades as f64;
+
+    // All triangles between the center and an edge have the same area, so pick one uniformly,
+    // then a uniform point inside it.
+    let k = rng::random_range(0..blades) as f64;
+    let a = Vec3::new(
+        f64::cos(rotation + k * wedge),
+        f64::sin(rotation + k * wedge),
+        0.0,
+    );
+    let b = Vec3::new(
+        f64::cos(rotation + (k + 1.0) * wedge),
+        f64::sin(rotation + (k + 1.0) * wedge),
+        0.0,
+    );
+
+    let (mut u, mut v) = (rng::random::<f64>(), rng::random::<f64>());
+    if u + v > 1.0 {
+        (u, v) = (1.0 - u, 1.0 - v);
+    }
+
+    u * a + v * b
+}
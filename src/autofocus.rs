@@ -0,0 +1,10 @@
+/// Where the camera measures the distance it focuses at, by casting a probe ray into the scene
+/// before rendering.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum AutoFocus {
+    /// The middle of the image
+    #[default]
+    Center,
+    /// The pixel in column `i` and row `j`
+    Pixel { i: i32, j: i32 },
+}
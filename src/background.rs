@@ -0,0 +1,56 @@
+use crate::{environment::EnvironmentLight, light::DirectionalLight, prelude::*, sky::Sky};
+
+/// Light arriving along rays that leave the scene.
+#[derive(Debug, Default, Clone)]
+pub enum Background {
+    /// Vertical blend from white at the horizon to light blue overhead
+    #[default]
+    Gradient,
+    /// The same color in every direction
+    Solid(Color),
+    /// Image based lighting, also sampled directly as a light
+    Environment(Rc<EnvironmentLight>),
+    /// Analytic daylight sky, whose sun is sampled directly as a light
+    Sky(Rc<Sky>),
+}
+
+impl Background {
+    /// Radiance arriving from `direction`.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Gradient => {
+                let unit_direction = unit_vector(direction);
+                let a = 0.5 * (unit_direction.y() + 1.0);
+                (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(color) => *color,
+            Background::Environment(environment) => environment.radiance(unit_vector(direction)),
+            Background::Sky(sky) => sky.radiance(direction),
+        }
+    }
+
+    /// Radiance from `direction` of a light source in the background that is otherwise only
+    /// sampled directly, for rays that could not have sampled it.
+    pub fn visible_light_radiance(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Sky(sky) => sky.sun_disk_radiance(direction),
+            _ => Color::default(),
+        }
+    }
+
+    /// The sun of a sky background, to sample as a light.
+    pub fn sun(&self) -> Option<DirectionalLight> {
+        match self {
+            Background::Sky(sky) => sky.sun_light(),
+            _ => None,
+        }
+    }
+
+    /// The environment light to sample directly, if any.
+    pub fn environment(&self) -> Option<&EnvironmentLight> {
+        match self {
+            Background::Environment(environment) => Some(environment),
+            _ => None,
+        }
+    }
+}
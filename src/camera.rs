@@ -1,5 +1,33 @@
-use crate::{hittable::Hittable, prelude::*};
+use std::{path::PathBuf, time::Instant};
 
+use crate::{
+    adaptive::AdaptiveSampling,
+    animation::{self, CameraAnimation, Sequence},
+    aov::Aov,
+    aperture::Aperture,
+    autofocus::AutoFocus,
+    background::Background,
+    checkpoint::{Checkpoint, Checkpointing, Progress, debug_hash},
+    denoise::Denoiser,
+    film::Film,
+    filter::Filter,
+    hittable::{HitRecord, Hittable},
+    image::Image,
+    lens::Lens,
+    light::Light,
+    light_list::LightList,
+    medium::{MAX_SCATTERING_EVENTS, MediumStack},
+    physical_camera::PhysicalCamera,
+    prelude::*,
+    progressive::Progressive,
+    projection::Projection,
+    spectrum::{self, Wavelengths},
+    stats::{self, ProgressBar, Stats},
+    stereo::{self, Eye, Stereo, StereoLayout},
+    tonemap::ToneMapping,
+};
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     /// Ratio of image width over height
     pub aspect_ratio: f64,
@@ -21,11 +49,55 @@ pub struct Camera {
     pub defocus_angle: f64,
     /// Distance from camera lookfrom point to plane of perfect focus
     pub focus_dist: f64,
+    /// Light sources sampled directly at every surface hit
+    pub lights: LightList,
+    /// Light arriving from outside the scene
+    pub background: Background,
+    /// Point whose distance `focus_dist` is set to before rendering, or `None` to keep it
+    pub autofocus: Option<AutoFocus>,
+    /// Real camera settings that replace `vfov` and `defocus_angle` and set the shutter time
+    /// and exposure, or `None` to use those directly
+    pub physical: Option<PhysicalCamera>,
+    /// How long the shutter stays open from time 0, in scene time for a still image and in
+    /// seconds for a sequence; moving objects blur over this interval
+    pub shutter_time: f64,
+    /// Keyframes of the camera parameters for sequence rendering, or `None` for a still camera
+    pub animation: Option<CameraAnimation>,
+    /// Lens prescription traced to generate camera rays, replacing the projection, `vfov`
+    /// and `defocus_angle`, or `None` for an ideal thin lens
+    pub lens: Option<Lens>,
+    /// Mapping from image positions to rays; depth of field applies to perspective only
+    pub projection: Projection,
+    /// Shape of the lens opening used for depth of field
+    pub aperture: Aperture,
+    /// Stereo rig rendering a left and a right eye, or `None` for a single view
+    pub stereo: Option<Stereo>,
+    /// Reconstruction filter used to weight samples into neighboring pixels
+    pub filter: Filter,
+    /// Adaptive sampling settings, or `None` to take `samples_per_pixel` samples everywhere
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Periodic checkpointing of the render, or `None` to keep all progress in memory
+    pub checkpoint: Option<Checkpointing>,
+    /// File the render statistics are written to as JSON when rendering finishes
+    pub stats_file: Option<PathBuf>,
+    /// Auxiliary outputs to produce alongside the beauty image, and the files they go to
+    pub aovs: Vec<(Aov, PathBuf)>,
+    /// Denoiser run on the final image, or `None` to output it as rendered
+    pub denoiser: Option<Denoiser>,
+    /// Exposure, tone mapping operator and transfer function for the LDR output
+    pub tone_mapping: ToneMapping,
+    /// Whether paths carry sampled wavelengths instead of RGB, for wavelength dependent
+    /// effects such as dispersion and measured metals
+    pub spectral: bool,
 
+    /// Eye currently being rendered by a stereo rig
+    eye: Option<Eye>,
+    /// Scene time at which the shutter opens, for the frame being rendered
+    frame_time: f64,
+    /// Scene time that passes per unit of `shutter_time`, for the frame being rendered
+    time_scale: f64,
     /// Rendered image height
     image_height: i32,
-    // Color scale factor for a sum of pixel samples
-    pixel_samples_scale: f64,
     /// Camera center
     center: Point3,
     /// Location of pixel 0, 0
@@ -59,8 +131,28 @@ impl Default for Camera {
             vup: Point3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            lights: LightList::new(),
+            background: Background::default(),
+            autofocus: None,
+            physical: None,
+            shutter_time: 0.0,
+            animation: None,
+            lens: None,
+            projection: Projection::default(),
+            aperture: Aperture::default(),
+            stereo: None,
+            filter: Filter::default(),
+            adaptive: None,
+            checkpoint: None,
+            stats_file: None,
+            aovs: Vec::new(),
+            denoiser: None,
+            tone_mapping: ToneMapping::default(),
+            spectral: false,
+            eye: None,
+            frame_time: 0.0,
+            time_scale: 1.0,
             image_height: Default::default(),
-            pixel_samples_scale: Default::default(),
             center: Default::default(),
             pixel00_loc: Default::default(),
             pixel_delta_u: Default::default(),
@@ -135,39 +227,736 @@ impl Camera {
         self
     }
 
+    pub fn with_lights(mut self, lights: LightList) -> Self {
+        self.lights = lights;
+
+        self
+    }
+
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+
+        self
+    }
+
+    pub fn with_autofocus(mut self, autofocus: AutoFocus) -> Self {
+        self.autofocus = Some(autofocus);
+
+        self
+    }
+
+    /// Moves `lookfrom` along the current view direction so that the bounding sphere of
+    /// `object`'s bounding box just fits the view, aiming at and focusing on its center.
+    pub fn frame_object(mut self, object: &impl Hittable) -> Self {
+        let Some((center, radius)) = Self::bounding_sphere(object) else {
+            return self;
+        };
+        let vfov = self
+            .physical
+            .map_or(self.vfov, |physical| physical.vfov(self.aspect_ratio));
+
+        // The narrower of the vertical and horizontal fields of view limits the size.
+        let half_vfov = (vfov / 2.0).to_radians();
+        let half_hfov = f64::atan(f64::tan(half_vfov) * self.aspect_ratio);
+        let distance = radius / f64::sin(f64::min(half_vfov, half_hfov));
+
+        let mut view = self.lookfrom - self.lookat;
+        if view.near_zero() {
+            warn!("The camera has no view direction, framing the object along -z.");
+            view = Vec3::new(0.0, 0.0, 1.0);
+        }
+        self.lookfrom = center + distance * unit_vector(view);
+        self.lookat = center;
+        self.focus_dist = distance;
+
+        self
+    }
+
+    /// Keeps `lookfrom` and sets `lookat` and `vfov` so that the bounding sphere of `object`'s
+    /// bounding box just fits the view, focusing on its center. A physical camera keeps the
+    /// field of view of its lens.
+    pub fn zoom_to_object(mut self, object: &impl Hittable) -> Self {
+        let Some((center, radius)) = Self::bounding_sphere(object) else {
+            return self;
+        };
+        let distance = (center - self.lookfrom).length();
+        if distance <= radius {
+            warn!("The camera is inside the object to zoom to, keeping the field of view.");
+            return self;
+        }
+
+        let half_angle = f64::asin(radius / distance);
+        let half_vfov = if self.aspect_ratio >= 1.0 {
+            half_angle
+        } else {
+            f64::atan(f64::tan(half_angle) / self.aspect_ratio)
+        };
+        self.vfov = 2.0 * half_vfov.to_degrees();
+        self.lookat = center;
+        self.focus_dist = distance;
+
+        self
+    }
+
+    /// Returns the center and radius of the sphere around `object`'s bounding box, or `None`
+    /// if it has no extent.
+    fn bounding_sphere(object: &impl Hittable) -> Option<(Point3, f64)> {
+        let bbox = object.bounding_box();
+        if bbox.is_empty() {
+            warn!("The object to frame has an empty bounding box.");
+            return None;
+        }
+
+        Some((bbox.center(), 0.5 * (bbox.max() - bbox.min()).length()))
+    }
+
+    pub fn with_physical_camera(mut self, physical: PhysicalCamera) -> Self {
+        self.physical = Some(physical);
+
+        self
+    }
+
+    pub fn with_shutter_time(mut self, shutter_time: f64) -> Self {
+        self.shutter_time = shutter_time;
+
+        self
+    }
+
+    pub fn with_animation(mut self, animation: CameraAnimation) -> Self {
+        self.animation = Some(animation);
+
+        self
+    }
+
+    pub fn with_lens(mut self, lens: Lens) -> Self {
+        self.lens = Some(lens);
+
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+
+        self
+    }
+
+    pub fn with_stereo(mut self, stereo: Stereo) -> Self {
+        self.stereo = Some(stereo);
+
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+
+        self
+    }
+
+    pub fn with_adaptive_sampling(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+
+        self
+    }
+
+    pub fn with_checkpoint(mut self, checkpoint: Checkpointing) -> Self {
+        self.checkpoint = Some(checkpoint);
+
+        self
+    }
+
+    pub fn with_stats_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stats_file = Some(path.into());
+
+        self
+    }
+
+    /// Requests the auxiliary output `aov`, written as a PFM image to `path` after rendering.
+    pub fn with_aov(mut self, aov: Aov, path: impl Into<PathBuf>) -> Self {
+        self.aovs.push((aov, path.into()));
+
+        self
+    }
+
+    pub fn with_denoiser(mut self, denoiser: Denoiser) -> Self {
+        self.denoiser = Some(denoiser);
+
+        self
+    }
+
+    pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.tone_mapping = tone_mapping;
+
+        self
+    }
+
+    pub fn with_spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+
+        self
+    }
+
     pub fn render(&mut self, world: &impl Hittable) -> std::io::Result<()> {
-        self.initialize();
+        stats::reset_counters();
+        self.render_image(world, std::io::stdout())?;
+        self.report_stats()?;
+        info!("Done.");
+
+        Ok(())
+    }
+
+    /// Renders every frame of `sequence` with the camera animation applied, each to its own
+    /// numbered file. Checkpoints and auxiliary outputs are numbered the same way.
+    pub fn render_sequence(
+        &self,
+        world: &impl Hittable,
+        sequence: &Sequence,
+    ) -> std::io::Result<()> {
+        stats::reset_counters();
+        for frame in sequence.frames() {
+            info!("Rendering frame {frame}.");
+
+            let mut camera = self.clone();
+            if let Some(animation) = camera.animation.take() {
+                animation.apply(&mut camera, frame as f64);
+            }
+            camera.frame_time = sequence.frame_time(frame);
+            camera.time_scale = sequence.time_scale();
+            if let Some(checkpoint) = &mut camera.checkpoint {
+                checkpoint.path = animation::frame_path(&checkpoint.path, frame);
+            }
+            if let Some(path) = camera
+                .adaptive
+                .as_mut()
+                .and_then(|adaptive| adaptive.sample_count_map.as_mut())
+            {
+                *path = animation::frame_path(path, frame);
+            }
+            for (_, path) in &mut camera.aovs {
+                *path = animation::frame_path(path, frame);
+            }
+            if let Some(Stereo {
+                layout: StereoLayout::SeparateFiles { left, right },
+                ..
+            }) = &mut camera.stereo
+            {
+                *left = animation::frame_path(left, frame);
+                *right = animation::frame_path(right, frame);
+            }
+
+            // A stereo rig writing each eye to its own file has nothing left for the frame file.
+            if let Some(Stereo {
+                layout: StereoLayout::SeparateFiles { .. },
+                ..
+            }) = &camera.stereo
+            {
+                camera.render_image(world, std::io::sink())?;
+                continue;
+            }
+            let path = animation::frame_path(&sequence.output, frame);
+            camera.render_image(
+                world,
+                std::io::BufWriter::new(std::fs::File::create(&path)?),
+            )?;
+            info!("Wrote {}.", path.display());
+        }
+        self.report_stats()?;
+        info!("Done.");
+
+        Ok(())
+    }
+
+    /// Renders the image, or both eyes of a stereo rig, and writes it to `out`.
+    fn render_image(
+        &mut self,
+        world: &impl Hittable,
+        out: impl std::io::Write,
+    ) -> std::io::Result<()> {
+        if let (Some(stereo), None) = (&self.stereo, self.eye) {
+            return self.render_stereo(world, &stereo.clone(), out);
+        }
+
+        let film = self.render_film(world)?;
+        self.develop(&film)?
+            .write_ppm(out, &self.output_tone_mapping())?;
+        self.write_aovs(&film)?;
 
-        println!("P3");
-        println!("{} {}", self.image_width, self.image_height);
-        println!("255");
-
-        for j in 0..self.image_height {
-            info!("Scanlines remaining: {}", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _sample in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += Self::ray_color(r, self.max_depth, world);
+        Ok(())
+    }
+
+    /// Renders both eyes of the stereo rig and writes them out packed as `stereo.layout` asks.
+    fn render_stereo(
+        &self,
+        world: &impl Hittable,
+        stereo: &Stereo,
+        out: impl std::io::Write,
+    ) -> std::io::Result<()> {
+        let mut images = Vec::new();
+        for eye in [Eye::Left, Eye::Right] {
+            info!("Rendering {} eye.", eye.name());
+
+            // Each eye keeps its own checkpoint and auxiliary outputs.
+            let mut camera = self.clone();
+            camera.eye = Some(eye);
+            if let Some(checkpoint) = &mut camera.checkpoint {
+                checkpoint.path = eye.suffixed(&checkpoint.path);
+            }
+            if let Some(path) = camera
+                .adaptive
+                .as_mut()
+                .and_then(|adaptive| adaptive.sample_count_map.as_mut())
+            {
+                *path = eye.suffixed(path);
+            }
+            for (_, path) in &mut camera.aovs {
+                *path = eye.suffixed(path);
+            }
+
+            let film = camera.render_film(world)?;
+            images.push(camera.develop(&film)?);
+            camera.write_aovs(&film)?;
+        }
+
+        let (left, right) = (&images[0], &images[1]);
+        match &stereo.layout {
+            StereoLayout::SideBySide => {
+                stereo::side_by_side(left, right).write_ppm(out, &self.output_tone_mapping())?
+            }
+            StereoLayout::TopBottom => {
+                stereo::top_bottom(left, right).write_ppm(out, &self.output_tone_mapping())?
+            }
+            StereoLayout::SeparateFiles {
+                left: left_path,
+                right: right_path,
+            } => {
+                for (image, path) in [(left, left_path), (right, right_path)] {
+                    let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+                    image.write_ppm(out, &self.output_tone_mapping())?;
+                    info!("Wrote {}.", path.display());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Samples the whole image, resuming from and saving checkpoints if enabled.
+    fn render_film(&mut self, world: &impl Hittable) -> std::io::Result<Film> {
+        self.prepare(world);
+
+        let start = Instant::now();
+        let scene_hash = debug_hash(world);
+        let (mut film, mut progress) = self.start_or_resume(scene_hash)?;
+        let mut last_checkpoint = Instant::now();
+
+        match self.adaptive.clone() {
+            Some(adaptive) => {
+                adaptive.validate()?;
+                let total_budget = self.samples_per_pixel as i64
+                    * self.image_width as i64
+                    * self.image_height as i64;
+                let mut bar = ProgressBar::new(total_budget as u64)
+                    .with_initial((total_budget - progress.budget) as u64);
+                self.render_adaptive(
+                    world,
+                    &mut film,
+                    &adaptive,
+                    &mut progress,
+                    |film, progress| {
+                        bar.set((total_budget - progress.budget) as u64);
+                        self.checkpoint_if_due(scene_hash, film, progress, &mut last_checkpoint)
+                    },
+                )?;
+                bar.finish();
+                if let Some(path) = &adaptive.sample_count_map {
+                    film.write_sample_count_map(std::fs::File::create(path)?)?;
                 }
-                write_color(std::io::stdout(), self.pixel_samples_scale * pixel_color)?;
             }
+            None => {
+                let mut bar =
+                    ProgressBar::new(self.image_height as u64).with_initial(progress.row as u64);
+                for j in progress.row..self.image_height {
+                    debug!("Scanlines remaining: {}", self.image_height - j);
+                    for i in 0..self.image_width {
+                        for _sample in 0..self.samples_per_pixel {
+                            self.sample_pixel(world, &mut film, i, j);
+                        }
+                    }
+                    progress.row = j + 1;
+                    bar.set(progress.row as u64);
+                    self.checkpoint_if_due(scene_hash, &film, progress, &mut last_checkpoint)?;
+                }
+                bar.finish();
+            }
+        }
+        self.remove_checkpoint()?;
+        stats::record_phase("render", start.elapsed());
+
+        Ok(film)
+    }
+
+    /// Renders successive passes over the whole image until one of the stopping conditions in
+    /// `progressive` is met, calling `on_pass` with the running average and the pass number
+    /// after every completed pass. Returns the film as it was when rendering stopped.
+    pub fn render_progressive(
+        &mut self,
+        world: &impl Hittable,
+        progressive: &Progressive,
+        mut on_pass: impl FnMut(&Film, i32),
+    ) -> Film {
+        stats::reset_counters();
+        self.prepare(world);
+
+        let mut film = self.new_film();
+        let start = Instant::now();
+        let out_of_time = || {
+            progressive
+                .time_limit
+                .is_some_and(|time_limit| start.elapsed() >= time_limit)
+        };
+
+        // Show progress towards whichever limit is closest to being reached, in permille.
+        let mut bar = ProgressBar::new(1000);
+        let permille_done = |samples: i32| {
+            let by_time = progressive.time_limit.map_or(0.0, |time_limit| {
+                start.elapsed().as_secs_f64() / time_limit.as_secs_f64()
+            });
+            let by_samples = progressive
+                .sample_limit
+                .map_or(0.0, |sample_limit| samples as f64 / sample_limit as f64);
+            (1000.0 * f64::max(by_time, by_samples)) as u64
+        };
+
+        let mut samples = 0;
+        for pass in 1.. {
+            let samples_this_pass = match progressive.sample_limit {
+                Some(sample_limit) => progressive.samples_per_pass.min(sample_limit - samples),
+                None => progressive.samples_per_pass,
+            };
+            if samples_this_pass <= 0 {
+                break;
+            }
+
+            for j in 0..self.image_height {
+                // Rows already sampled this pass simply carry one more batch than the rest.
+                if progressive.is_cancelled() || out_of_time() {
+                    bar.finish();
+                    info!("Stopped during pass {pass} after {samples} samples per pixel.");
+                    stats::record_phase("render", start.elapsed());
+                    if let Err(err) = self.report_stats() {
+                        warn!("Could not write render statistics: {err}");
+                    }
+                    return film;
+                }
+                bar.set(permille_done(samples));
+                for i in 0..self.image_width {
+                    for _sample in 0..samples_this_pass {
+                        self.sample_pixel(world, &mut film, i, j);
+                    }
+                }
+            }
+
+            samples += samples_this_pass;
+            info!("Pass {pass} done: {samples} samples per pixel");
+            on_pass(&film, pass);
+        }
+        bar.finish();
+        stats::record_phase("render", start.elapsed());
+        if let Err(err) = self.report_stats() {
+            warn!("Could not write render statistics: {err}");
         }
         info!("Done.");
 
+        film
+    }
+
+    fn render_adaptive(
+        &self,
+        world: &impl Hittable,
+        film: &mut Film,
+        adaptive: &AdaptiveSampling,
+        progress: &mut Progress,
+        mut on_pass: impl FnMut(&Film, Progress) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        // The total budget is the same as for uniform sampling; whatever converged pixels leave
+        // unused is spent on the remaining ones.
+        while progress.budget > 0 {
+            let mut active = 0;
+            for j in 0..self.image_height {
+                for i in 0..self.image_width {
+                    let samples = film.samples(i, j);
+                    if progress.budget <= 0 || !adaptive.needs_samples(samples, film.error(i, j)) {
+                        continue;
+                    }
+
+                    let batch = adaptive
+                        .batch_size
+                        .min(adaptive.max_samples - samples)
+                        .max(adaptive.min_samples - samples);
+                    for _sample in 0..batch {
+                        self.sample_pixel(world, film, i, j);
+                    }
+                    progress.budget -= batch as i64;
+                    active += 1;
+                }
+            }
+
+            progress.pass += 1;
+            info!("Adaptive pass {}: {active} pixels sampled", progress.pass);
+            if active == 0 {
+                break;
+            }
+            on_pass(film, *progress)?;
+        }
+
         Ok(())
     }
 
+    /// Returns an empty film, or the film and progress of the checkpoint being resumed.
+    fn start_or_resume(&self, scene_hash: u64) -> std::io::Result<(Film, Progress)> {
+        let fresh = (
+            self.new_film(),
+            Progress {
+                budget: self.samples_per_pixel as i64
+                    * self.image_width as i64
+                    * self.image_height as i64,
+                ..Default::default()
+            },
+        );
+
+        let Some(checkpointing) = self.checkpoint.as_ref().filter(|c| c.resume) else {
+            return Ok(fresh);
+        };
+        if !checkpointing.path.exists() {
+            info!(
+                "No checkpoint at {}, starting a new render.",
+                checkpointing.path.display()
+            );
+            return Ok(fresh);
+        }
+
+        let checkpoint = Checkpoint::load(&checkpointing.path, self.filter)?;
+        if checkpoint.scene_hash != scene_hash {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "refusing to resume: the checkpoint was saved for a different scene",
+            ));
+        }
+        if checkpoint.camera_hash != self.settings_hash() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "refusing to resume: the checkpoint was saved with different camera settings",
+            ));
+        }
+
+        info!("Resuming from {}.", checkpointing.path.display());
+        rng::set_state(checkpoint.rng);
+
+        Ok((checkpoint.film, checkpoint.progress))
+    }
+
+    /// Saves a checkpoint if checkpointing is enabled and its interval has passed.
+    fn checkpoint_if_due(
+        &self,
+        scene_hash: u64,
+        film: &Film,
+        progress: Progress,
+        last_checkpoint: &mut Instant,
+    ) -> std::io::Result<()> {
+        let Some(checkpointing) = &self.checkpoint else {
+            return Ok(());
+        };
+        if last_checkpoint.elapsed() < checkpointing.interval {
+            return Ok(());
+        }
+
+        Checkpoint {
+            scene_hash,
+            camera_hash: self.settings_hash(),
+            rng: rng::state(),
+            progress,
+            film: film.clone(),
+        }
+        .save(&checkpointing.path)?;
+        *last_checkpoint = Instant::now();
+        info!("Saved checkpoint to {}.", checkpointing.path.display());
+
+        Ok(())
+    }
+
+    /// Deletes the checkpoint of a finished render, so that it is not resumed again.
+    fn remove_checkpoint(&self) -> std::io::Result<()> {
+        let Some(checkpointing) = &self.checkpoint else {
+            return Ok(());
+        };
+        if !checkpointing.path.exists() {
+            return Ok(());
+        }
+
+        std::fs::remove_file(&checkpointing.path)?;
+        info!("Removed checkpoint {}.", checkpointing.path.display());
+
+        Ok(())
+    }
+
+    /// Resolves `film` into the final image, denoising it if a denoiser is set.
+    pub fn develop(&self, film: &Film) -> std::io::Result<Image> {
+        let image = film.image();
+        let Some(denoiser) = &self.denoiser else {
+            return Ok(image);
+        };
+        let Some(aovs) = &film.aovs else {
+            warn!("The film has no auxiliary buffers to guide the denoiser, skipping it.");
+            return Ok(image);
+        };
+
+        if let Some(path) = &denoiser.raw_output {
+            image.write_ppm(
+                std::io::BufWriter::new(std::fs::File::create(path)?),
+                &self.output_tone_mapping(),
+            )?;
+            info!("Wrote raw image to {}.", path.display());
+        }
+
+        Ok(stats::time_phase("denoise", || {
+            denoiser.denoise(&image, aovs)
+        }))
+    }
+
+    /// Tone mapping for the output, including the exposure of the physical camera if any.
+    fn output_tone_mapping(&self) -> ToneMapping {
+        let physical_exposure = self.physical.map_or(0.0, |physical| physical.exposure());
+
+        self.tone_mapping
+            .with_exposure(self.tone_mapping.exposure + physical_exposure)
+    }
+
+    fn new_film(&self) -> Film {
+        let film = Film::new(self.image_width, self.image_height, self.filter);
+        if self.aovs.is_empty() && self.denoiser.is_none() {
+            film
+        } else {
+            film.with_aovs()
+        }
+    }
+
+    /// Writes every requested auxiliary output of `film` to its file.
+    pub fn write_aovs(&self, film: &Film) -> std::io::Result<()> {
+        let Some(buffers) = &film.aovs else {
+            return Ok(());
+        };
+        for (aov, path) in &self.aovs {
+            let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+            buffers.write_pfm(*aov, out)?;
+            info!("Wrote {} to {}.", aov.name(), path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Logs a summary of the render statistics and writes them to the stats file, if any. The
+    /// timed phases are cleared, so the next render reports only its own.
+    fn report_stats(&self) -> std::io::Result<()> {
+        let stats = Stats::snapshot();
+        stats::clear_phases();
+        info!(
+            "{} camera, {} bounce and {} shadow rays, {} intersection tests",
+            stats.camera_rays, stats.bounce_rays, stats.shadow_rays, stats.intersection_tests
+        );
+        info!(
+            "Average path length {:.2}, {:.0} samples/sec",
+            stats.average_path_length(),
+            stats.samples_per_second()
+        );
+
+        if let Some(path) = &self.stats_file {
+            stats.write_json(std::fs::File::create(path)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Hash of every setting that affects the rendered image.
+    fn settings_hash(&self) -> u64 {
+        let mut camera = self.clone();
+        camera.checkpoint = None;
+        camera.stats_file = None;
+        for (_, path) in &mut camera.aovs {
+            *path = PathBuf::new();
+        }
+        // Only whether there is a denoiser matters, since it needs the auxiliary buffers.
+        camera.denoiser = camera.denoiser.map(|_| Denoiser::default());
+        camera.tone_mapping = ToneMapping::default();
+        if let Some(adaptive) = &mut camera.adaptive {
+            adaptive.sample_count_map = None;
+        }
+
+        debug_hash(&camera)
+    }
+
+    fn sample_pixel(&self, world: &impl Hittable, film: &mut Film, i: i32, j: i32) {
+        let offset = self.filter.sample_offset();
+        let wavelengths = self.spectral.then(Wavelengths::sample);
+        let sample = match &self.lens {
+            Some(lens) => self.get_lens_ray(lens, i, j, offset, wavelengths),
+            None => self
+                .get_ray(i, j, offset)
+                .map(|r| (r.with_wavelengths(wavelengths), Color::new(1.0, 1.0, 1.0))),
+        };
+        // The first hit is shared between the auxiliary outputs and the path traced from it.
+        let hit = sample.and_then(|(r, _)| world.hit(r, Interval::new(0.001, INFINITY)));
+        if let Some(aovs) = &mut film.aovs {
+            // Depth is measured from the origin of the camera ray, which is where the eye being
+            // rendered is, also for omnidirectional stereo.
+            let depth = sample
+                .zip(hit.as_ref())
+                .map_or(0.0, |((r, _), rec)| dot(rec.p - r.origin(), -self.w));
+            aovs.add_sample(i, j, hit.as_ref(), depth);
+        }
+
+        // Image positions outside the projection stay black.
+        let sample_color = sample.map_or(Color::default(), |(r, weight)| {
+            let color =
+                weight * self.hit_color(r, hit, self.max_depth, world, None, &MediumStack::new());
+            match r.wavelengths() {
+                Some(wavelengths) => wavelengths.to_rgb(color),
+                None => color,
+            }
+        });
+        film.add_sample(i, j, offset, sample_color);
+    }
+
+    /// Sets the camera up for rendering `world`: derives the view, focuses on the autofocus
+    /// point and, last so that it happens only once, focuses the lens.
+    fn prepare(&mut self, world: &impl Hittable) {
+        self.initialize();
+        self.autofocus(world);
+        if let Some(lens) = &mut self.lens {
+            lens.focus(
+                self.focus_dist,
+                self.image_width as f64 / self.image_height as f64,
+            );
+        }
+    }
+
     fn initialize(&mut self) {
         self.image_height = {
             let image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
             if image_height < 1 { 1 } else { image_height }
         };
 
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
-
         self.center = self.lookfrom;
 
+        if let Some(physical) = self.physical {
+            self.vfov = physical.vfov(self.image_width as f64 / self.image_height as f64);
+            self.defocus_angle = physical.defocus_angle(self.focus_dist);
+            self.shutter_time = physical.shutter_speed;
+        }
         // Determine viewport dimensions.
         let theta = self.vfov.to_radians();
         let h = f64::tan(theta / 2.0);
@@ -199,32 +988,177 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        // Construct a camera ray originating from the defocus disk and directed at a randomly
-        // sampled point around the pixel location i, j.
+    /// Sets `focus_dist` to the depth of the surface seen at the autofocus point, if enabled.
+    fn autofocus(&mut self, world: &impl Hittable) {
+        let Some(autofocus) = self.autofocus else {
+            return;
+        };
+        let (i, j) = match autofocus {
+            AutoFocus::Center => (self.image_width / 2, self.image_height / 2),
+            AutoFocus::Pixel { i, j } => (i, j),
+        };
+
+        // Probe along the pixel center's pinhole ray, without depth of field.
+        let direction = match self.projection {
+            Projection::Perspective => Some(
+                self.pixel00_loc
+                    + (i as f64 * self.pixel_delta_u)
+                    + (j as f64 * self.pixel_delta_v)
+                    - self.center,
+            ),
+            projection => {
+                let s = (i as f64 + 0.5) / self.image_width as f64;
+                let t = (j as f64 + 0.5) / self.image_height as f64;
+                let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+                projection
+                    .camera_ray(s, t, aspect_ratio)
+                    .map(|(_, direction)| self.camera_to_world(direction))
+            }
+        };
+        let probe =
+            direction.map(|direction| Ray::new_with_time(self.center, direction, self.frame_time));
+        let Some(rec) = probe.and_then(|r| world.hit(r, Interval::new(0.001, INFINITY))) else {
+            warn!("The autofocus probe at pixel {i}, {j} hit nothing, keeping the focus distance.");
+            return;
+        };
 
-        let offset = Self::sample_square();
+        let depth = dot(rec.p - self.center, -self.w);
+        if depth <= 0.0 {
+            warn!(
+                "The autofocus probe at pixel {i}, {j} hit behind the camera, keeping the focus distance."
+            );
+            return;
+        }
+        self.focus_dist = depth;
+        info!(
+            "Autofocus set the focus distance to {:.3}.",
+            self.focus_dist
+        );
+        self.initialize();
+    }
+
+    fn get_ray(&self, i: i32, j: i32, offset: Vec3) -> Option<Ray> {
+        if !matches!(self.projection, Projection::Perspective) {
+            let s = (i as f64 + 0.5 + offset.x()) / self.image_width as f64;
+            let t = (j as f64 + 0.5 + offset.y()) / self.image_height as f64;
+            let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+            let (origin, direction) = self.projection.camera_ray(s, t, aspect_ratio)?;
+            let origin = match (self.projection, self.eye) {
+                (Projection::Equirectangular, Some(eye)) => {
+                    origin + self.ods_offset(eye, direction)
+                }
+                _ => origin + self.eye_offset(),
+            };
+
+            stats::CAMERA_RAYS.inc();
+            return Some(Ray::new_with_time(
+                self.center + self.camera_to_world(origin),
+                self.camera_to_world(direction),
+                self.sample_time(),
+            ));
+        }
+
+        // Construct a camera ray originating from the defocus disk and directed at the point
+        // offset from the pixel location i, j.
+
+        // A stereo eye shifts its view of the focus plane so that both eyes' views coincide at
+        // the convergence distance.
+        let eye_offset = self.camera_to_world(self.eye_offset());
+        let convergence_shift = match &self.stereo {
+            Some(stereo) => 1.0 - self.focus_dist / stereo.convergence_distance,
+            None => 0.0,
+        };
         let pixel_sample = self.pixel00_loc
             + ((i as f64 + offset.x()) * self.pixel_delta_u)
-            + ((j as f64 + offset.y()) * self.pixel_delta_v);
+            + ((j as f64 + offset.y()) * self.pixel_delta_v)
+            + convergence_shift * eye_offset;
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else {
-            self.defocus_disk_sample()
-        };
+        let ray_origin = eye_offset
+            + if self.defocus_angle <= 0.0 {
+                self.center
+            } else {
+                // Image position in [-1, 1] with y up, for the aperture's optical vignetting.
+                let x = 2.0 * (i as f64 + 0.5 + offset.x()) / self.image_width as f64 - 1.0;
+                let y = 1.0 - 2.0 * (j as f64 + 0.5 + offset.y()) / self.image_height as f64;
+                self.defocus_disk_sample(x, y)?
+            };
         let ray_direction = pixel_sample - ray_origin;
 
-        Ray::new(ray_origin, ray_direction)
+        stats::CAMERA_RAYS.inc();
+        Some(Ray::new_with_time(
+            ray_origin,
+            ray_direction,
+            self.sample_time(),
+        ))
     }
 
-    fn sample_square() -> Vec3 {
-        // Returns the vector to a random point in the [-.5,-.5]-[+.5,+.5] unit square.
-        Vec3::new(
-            rand::random::<f64>() - 0.5,
-            rand::random::<f64>() - 0.5,
-            0.0,
+    /// Returns a ray traced through `lens` for pixel `i`, `j`, and the weight of its color.
+    fn get_lens_ray(
+        &self,
+        lens: &Lens,
+        i: i32,
+        j: i32,
+        offset: Vec3,
+        mut wavelengths: Option<Wavelengths>,
+    ) -> Option<(Ray, Color)> {
+        let s = (i as f64 + 0.5 + offset.x()) / self.image_width as f64;
+        let t = (j as f64 + 0.5 + offset.y()) / self.image_height as f64;
+
+        // A dispersive lens sends each wavelength in its own direction, so only one is traced.
+        let mut channel = None;
+        let (wavelength, dispersion_weight) = if lens.is_dispersive() {
+            let (wavelength, weight) =
+                spectrum::single_wavelength(wavelengths.as_mut(), &mut channel);
+            (Some(wavelength), weight)
+        } else {
+            (None, Color::new(1.0, 1.0, 1.0))
+        };
+        let (origin, direction, weight) = lens.camera_ray(s, t, wavelength)?;
+
+        stats::CAMERA_RAYS.inc();
+        let r = Ray::new_with_time(
+            self.center + self.camera_to_world(origin + self.eye_offset()),
+            self.camera_to_world(direction),
+            self.sample_time(),
         )
+        .with_wavelengths(wavelengths)
+        .with_channel(channel);
+        Some((r, weight * dispersion_weight))
+    }
+
+    /// Returns a random time while the shutter is open.
+    fn sample_time(&self) -> f64 {
+        self.frame_time + self.time_scale * self.shutter_time * rng::random::<f64>()
+    }
+
+    /// Returns the camera-frame position of the eye being rendered relative to the rig center.
+    fn eye_offset(&self) -> Vec3 {
+        match (&self.stereo, self.eye) {
+            (Some(stereo), Some(eye)) => {
+                Vec3::new(eye.sign() * stereo.interpupillary_distance / 2.0, 0.0, 0.0)
+            }
+            _ => Vec3::default(),
+        }
+    }
+
+    /// Returns the camera-frame eye position for an omnidirectional stereo ray in `direction`:
+    /// on a circle of the interpupillary diameter, at right angles to the horizontal direction.
+    fn ods_offset(&self, eye: Eye, direction: Vec3) -> Vec3 {
+        let Some(stereo) = &self.stereo else {
+            return Vec3::default();
+        };
+        let horizontal = Vec3::new(direction.x(), 0.0, direction.z());
+        if horizontal.near_zero() {
+            return Vec3::default();
+        }
+
+        // The right-hand side of the horizontal direction, with y up.
+        let right = unit_vector(cross(horizontal, Vec3::new(0.0, 1.0, 0.0)));
+        eye.sign() * stereo.interpupillary_distance / 2.0 * right
+    }
+
+    fn camera_to_world(&self, v: Vec3) -> Vec3 {
+        v.x() * self.u + v.y() * self.v + v.z() * self.w
     }
 
     fn _sample_disk(radius: f64) -> Vec3 {
@@ -232,28 +1166,218 @@ impl Camera {
         radius * random_in_unit_disk()
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        // Returns a random point in the camera defocus disk.
-        let p = random_in_unit_disk();
+    fn defocus_disk_sample(&self, x: f64, y: f64) -> Option<Point3> {
+        // Returns a random point on the camera aperture, scaled to the defocus disk, or `None`
+        // if the aperture is vignetted there.
+        let p = self.aperture.sample(x, y)?;
+
+        Some(self.center + (p[0] * self.defocus_disk_u) + (p[1] * self.defocus_disk_v))
+    }
+
+    /// Returns the light from every light source scattered towards the origin of `r` at `rec`,
+    /// tracing a shadow ray to each.
+    fn direct_light(&self, r: Ray, rec: &HitRecord, world: &impl Hittable) -> Color {
+        let mut color = Color::default();
+
+        // The environment is also reached by scattered rays, so both estimates are combined
+        // with multiple importance sampling.
+        if let Some((direction, radiance, light_pdf)) =
+            self.background.environment().and_then(|env| env.sample())
+        {
+            let f = rec.mat.eval(r, rec, direction);
+            if !f.near_zero() {
+                stats::SHADOW_RAYS.inc();
+                let shadow_ray = Ray::new_with_time(rec.p, direction, r.time());
+                if world
+                    .hit(shadow_ray, Interval::new(0.001, INFINITY))
+                    .is_none()
+                {
+                    let scatter_pdf = rec.mat.pdf(r, rec, direction);
+                    let weight = power_heuristic(light_pdf, scatter_pdf);
+                    let radiance = spectrum::upsample(radiance, r.wavelengths());
+                    color += (weight / light_pdf) * f * radiance;
+                }
+            }
+        }
+
+        let sun = self.background.sun();
+        let lights = self
+            .lights
+            .lights
+            .iter()
+            .map(|light| light.as_ref())
+            .chain(sun.as_ref().map(|sun| sun as &dyn Light));
+        for light in lights {
+            let Some(sample) = light.sample(rec.p) else {
+                continue;
+            };
+            let f = rec.mat.eval(r, rec, sample.direction);
+            if f.near_zero() {
+                continue;
+            }
+
+            stats::SHADOW_RAYS.inc();
+            let shadow_ray = Ray::new_with_time(rec.p, sample.direction, r.time());
+            if world
+                .hit(shadow_ray, Interval::new(0.001, sample.distance - 0.001))
+                .is_none()
+            {
+                color += f * spectrum::upsample(sample.irradiance, r.wavelengths());
+            }
+        }
 
-        self.center + (p[0] * self.defocus_disk_u) + (p[1] * self.defocus_disk_v)
+        color
     }
 
-    fn ray_color(r: Ray, depth: i32, world: &impl Hittable) -> Color {
+    /// Returns the light arriving along `r`. `scatter_pdf` is the density with which a
+    /// non-specular scattering event chose `r`, used to weight light sources that were also
+    /// sampled directly. `media` are the transparent objects `r` travels inside.
+    fn ray_color(
+        &self,
+        r: Ray,
+        depth: i32,
+        world: &impl Hittable,
+        scatter_pdf: Option<f64>,
+        media: &MediumStack,
+    ) -> Color {
         // If we've exceeded the ray bounce limit, no more light is gathered.
         if depth <= 0 {
             return Color::new(0.0, 0.0, 0.0);
         }
 
-        if let Some(rec) = world.hit(r, Interval::new(0.001, INFINITY)) {
-            if let Some((scattered, attenuation)) = rec.mat.scatter(r, rec.clone()) {
-                return attenuation * Self::ray_color(scattered, depth - 1, world);
-            }
+        let hit = world.hit(r, Interval::new(0.001, INFINITY));
+        self.hit_color(r, hit, depth, world, scatter_pdf, media)
+    }
+
+    /// Returns the light arriving along `r` like [`Camera::ray_color`], given the nearest
+    /// surface `hit` by it, if any.
+    fn hit_color(
+        &self,
+        r: Ray,
+        hit: Option<HitRecord>,
+        depth: i32,
+        world: &impl Hittable,
+        scatter_pdf: Option<f64>,
+        media: &MediumStack,
+    ) -> Color {
+        if depth <= 0 {
             return Color::new(0.0, 0.0, 0.0);
         }
 
-        let unit_direction = unit_vector(r.direction());
-        let a = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+        let Some((r, hit, walk_weight, walked)) = self.walk_medium(r, hit, world, media) else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+        let scatter_pdf = if walked { None } else { scatter_pdf };
+
+        if let Some(mut rec) = hit {
+            let transmittance = walk_weight
+                * media.current().map_or(Color::new(1.0, 1.0, 1.0), |medium| {
+                    medium.transmittance(&r, rec.t * r.direction().length())
+                });
+
+            let crosses_media = rec.mat.priority().is_some();
+            if crosses_media {
+                // Surfaces inside a higher priority object are not there for the light.
+                if !media.is_interface(&rec) {
+                    let continued = Ray::new_with_time(rec.p, r.direction(), r.time())
+                        .with_wavelengths(r.wavelengths().copied())
+                        .with_channel(r.channel());
+                    let media = media.crossed(&rec);
+                    return transmittance
+                        * self.ray_color(continued, depth - 1, world, scatter_pdf, &media);
+                }
+            }
+            // Opaque surfaces see the medium they are in too, e.g. a coated metal under water.
+            rec.outer_ior = media
+                .outside(&rec)
+                .map_or(1.0, |medium| medium.ior(r.wavelength()));
+
+            let direct = self.direct_light(r, &rec, world);
+            if let Some((mut scattered, attenuation, pdf)) = rec.mat.sample(r, rec.clone()) {
+                stats::BOUNCE_RAYS.inc();
+                // Scattered rays keep the path's wavelengths unless the material changed them.
+                if scattered.wavelengths().is_none() {
+                    scattered = scattered.with_wavelengths(r.wavelengths().copied());
+                }
+                if scattered.channel().is_none() {
+                    scattered = scattered.with_channel(r.channel());
+                }
+                // Transmitted rays leave or enter the object that was hit.
+                let crossed;
+                let scattered_media =
+                    if crosses_media && dot(scattered.direction(), rec.normal) < 0.0 {
+                        crossed = media.crossed(&rec);
+                        &crossed
+                    } else {
+                        media
+                    };
+                let scattered_color =
+                    self.ray_color(scattered, depth - 1, world, pdf, scattered_media);
+                return transmittance * (direct + attenuation * scattered_color);
+            }
+            return transmittance * direct;
+        }
+
+        let radiance = self.background.radiance(r.direction());
+        let radiance = match (self.background.environment(), scatter_pdf) {
+            (Some(environment), Some(scatter_pdf)) => {
+                let light_pdf = environment.pdf(unit_vector(r.direction()));
+                power_heuristic(scatter_pdf, light_pdf) * radiance
+            }
+            // Rays that did not come from a diffuse bounce can see lights that are otherwise
+            // only sampled directly.
+            (_, None) => radiance + self.background.visible_light_radiance(r.direction()),
+            _ => radiance,
+        };
+        walk_weight * spectrum::upsample(radiance, r.wavelengths())
     }
+
+    /// Follows `r`, whose nearest hit is `hit`, through the scattering medium it travels in, if
+    /// any, from one scattering event to the next until it reaches a surface or leaves the
+    /// scene. Returns the last ray, its hit, the weight of the light carried along the walk and
+    /// whether it scattered at all, or `None` if the light is given up as absorbed.
+    fn walk_medium(
+        &self,
+        mut r: Ray,
+        mut hit: Option<HitRecord>,
+        world: &impl Hittable,
+        media: &MediumStack,
+    ) -> Option<(Ray, Option<HitRecord>, Color, bool)> {
+        let Some(scattering) = media.current().and_then(|medium| medium.scattering()) else {
+            return Some((r, hit, Color::new(1.0, 1.0, 1.0), false));
+        };
+
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        for events in 0..MAX_SCATTERING_EVENTS {
+            let distance = hit
+                .as_ref()
+                .map_or(INFINITY, |rec| rec.t * r.direction().length());
+            let (event, event_weight) = scattering.sample_distance(&r, distance, weight);
+            weight = weight * event_weight;
+            let Some(t) = event else {
+                return Some((r, hit, weight, events > 0));
+            };
+            if weight.near_zero() {
+                return None;
+            }
+
+            let direction = scattering.sample_direction(unit_vector(r.direction()));
+            r = Ray::new_with_time(r.at(t / r.direction().length()), direction, r.time())
+                .with_wavelengths(r.wavelengths().copied())
+                .with_channel(r.channel());
+            hit = world.hit(r, Interval::new(0.001, INFINITY));
+        }
+
+        // Light still inside after this many events is given up as absorbed, which slightly
+        // darkens dense media of high albedo.
+        None
+    }
+}
+
+/// Multiple importance sampling weight of a sample drawn with density `pdf_f` that could also
+/// have been drawn with density `pdf_g`.
+fn power_heuristic(pdf_f: f64, pdf_g: f64) -> f64 {
+    let f2 = pdf_f * pdf_f;
+    let g2 = pdf_g * pdf_g;
+    if f2 + g2 == 0.0 { 0.0 } else { f2 / (f2 + g2) }
 }
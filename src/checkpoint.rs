@@ -0,0 +1,185 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{film::Film, filter::Filter, rng::RngState};
+
+const MAGIC: &[u8; 6] = b"RTCKPT";
+/// Version of the checkpoint layout, written after `MAGIC` as two ASCII digits. Bump it
+/// whenever the layout changes, so that older checkpoints are rejected instead of misread.
+const VERSION: u32 = 3;
+
+/// Settings for periodically saving an in-progress render so it can be resumed later.
+#[derive(Debug, Clone)]
+pub struct Checkpointing {
+    /// File the checkpoint is written to and resumed from
+    pub path: PathBuf,
+    /// Minimum wall-clock time between two checkpoints
+    pub interval: Duration,
+    /// Continue from an existing checkpoint at `path` instead of starting over
+    pub resume: bool,
+}
+
+impl Checkpointing {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(300),
+            resume: false,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+
+        self
+    }
+
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+
+        self
+    }
+}
+
+/// How far a render got. Uniform renders advance `row`, adaptive renders advance `pass` and
+/// spend `budget`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Progress {
+    pub row: i32,
+    pub pass: i32,
+    pub budget: i64,
+}
+
+/// Everything needed to continue a render exactly where it stopped.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub camera_hash: u64,
+    pub rng: RngState,
+    pub progress: Progress,
+    pub film: Film,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint to `path`. The file is replaced atomically, so a crash while
+    /// saving leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp_path)?);
+            out.write_all(MAGIC)?;
+            write!(out, "{VERSION:02}")?;
+            write_u64(&mut out, self.scene_hash)?;
+            write_u64(&mut out, self.camera_hash)?;
+            out.write_all(&self.rng.seed)?;
+            write_u64(&mut out, self.rng.stream)?;
+            out.write_all(&self.rng.word_pos.to_le_bytes())?;
+            write_i32(&mut out, self.progress.row)?;
+            write_i32(&mut out, self.progress.pass)?;
+            write_u64(&mut out, self.progress.budget as u64)?;
+            self.film.write_state(&mut out)?;
+            out.flush()?;
+        }
+
+        std::fs::rename(tmp_path, path)
+    }
+
+    pub fn load(path: &Path, filter: Filter) -> std::io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 6];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not a render checkpoint", path.display()),
+            ));
+        }
+        let mut version = [0; 2];
+        input.read_exact(&mut version)?;
+        let version = std::str::from_utf8(&version)
+            .ok()
+            .and_then(|version| version.parse::<u32>().ok());
+        if version != Some(VERSION) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} was saved in checkpoint format {}, but this build reads format {VERSION}",
+                    path.display(),
+                    version.map_or("unknown".to_string(), |version| version.to_string()),
+                ),
+            ));
+        }
+
+        let scene_hash = read_u64(&mut input)?;
+        let camera_hash = read_u64(&mut input)?;
+        let mut seed = [0; 32];
+        input.read_exact(&mut seed)?;
+        let stream = read_u64(&mut input)?;
+        let mut word_pos = [0; 16];
+        input.read_exact(&mut word_pos)?;
+        let rng = RngState {
+            seed,
+            stream,
+            word_pos: u128::from_le_bytes(word_pos),
+        };
+        let progress = Progress {
+            row: read_i32(&mut input)?,
+            pass: read_i32(&mut input)?,
+            budget: read_u64(&mut input)? as i64,
+        };
+        let film = Film::read_state(&mut input, filter)?;
+
+        Ok(Self {
+            scene_hash,
+            camera_hash,
+            rng,
+            progress,
+            film,
+        })
+    }
+}
+
+/// Hashes the `Debug` representation of `value` with 64-bit FNV-1a, which, unlike the standard
+/// library hasher, is stable between builds.
+pub fn debug_hash(value: &impl std::fmt::Debug) -> u64 {
+    format!("{value:?}")
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+pub(crate) fn write_u64(out: &mut impl Write, value: u64) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_i32(out: &mut impl Write, value: i32) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_f64(out: &mut impl Write, value: f64) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn read_u64(input: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_i32(input: &mut impl Read) -> std::io::Result<i32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_f64(input: &mut impl Read) -> std::io::Result<f64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{prelude::*, tonemap::ToneMapping};
 
 pub type Color = Vec3;
 
@@ -11,15 +11,31 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     }
 }
 
-pub fn write_color(mut out: impl std::io::Write, pixel_color: Color) -> std::io::Result<()> {
-    let r = pixel_color.x();
-    let g = pixel_color.y();
-    let b = pixel_color.z();
+#[inline]
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+/// Converts CIE XYZ tristimulus values to linear sRGB.
+#[inline]
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    Color::new(
+        3.2406 * xyz.x() - 1.5372 * xyz.y() - 0.4986 * xyz.z(),
+        -0.9689 * xyz.x() + 1.8758 * xyz.y() + 0.0415 * xyz.z(),
+        0.0557 * xyz.x() - 0.2040 * xyz.y() + 1.0570 * xyz.z(),
+    )
+}
 
-    // Apply a linear to gamma transform for gamma 2
-    let r = linear_to_gamma(r);
-    let g = linear_to_gamma(g);
-    let b = linear_to_gamma(b);
+pub fn write_color(
+    mut out: impl std::io::Write,
+    pixel_color: Color,
+    tone_mapping: &ToneMapping,
+) -> std::io::Result<()> {
+    // Apply exposure, the tone mapping operator and the transfer function
+    let display_color = tone_mapping.apply(pixel_color);
+    let r = display_color.x();
+    let g = display_color.y();
+    let b = display_color.z();
 
     // Translate the [0,1] component values to the byte range [0,255].
     const INTENSITY: Interval = Interval::new(0.000, 0.999);
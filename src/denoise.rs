@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use crate::{
+    aov::{Aov, AovBuffers},
+    image::Image,
+    prelude::*,
+};
+
+/// Edge-avoiding à-trous wavelet denoiser guided by the albedo, normal and depth buffers.
+///
+/// Each iteration blurs with a 5x5 B3-spline kernel whose taps are spread twice as far apart
+/// as in the previous one, while the guide buffers keep the blur from crossing edges. Texture
+/// detail is preserved by filtering the illumination (the color divided by the albedo).
+#[derive(Debug, Clone)]
+pub struct Denoiser {
+    /// Scales how different two colors may be and still be averaged; 0 leaves the image as is
+    pub strength: f64,
+    /// Number of wavelet iterations, each doubling the filter footprint
+    pub iterations: i32,
+    /// Tolerance for differences between shading normals
+    pub sigma_normal: f64,
+    /// Tolerance for relative differences in depth
+    pub sigma_depth: f64,
+    /// Optional path the raw, undenoised image is written to as a PPM
+    pub raw_output: Option<PathBuf>,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            iterations: 5,
+            sigma_normal: 0.1,
+            sigma_depth: 0.05,
+            raw_output: None,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn with_strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: i32) -> Self {
+        self.iterations = iterations;
+
+        self
+    }
+
+    pub fn with_raw_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.raw_output = Some(path.into());
+
+        self
+    }
+
+    pub fn denoise(&self, image: &Image, aovs: &AovBuffers) -> Image {
+        if self.strength <= 0.0 {
+            return image.clone();
+        }
+
+        // Divide out the albedo so only the noisy illumination is filtered.
+        let albedo = |i, j| {
+            let albedo = aovs.value(Aov::Albedo, i, j);
+            Color::new(
+                f64::max(albedo.x(), 0.01),
+                f64::max(albedo.y(), 0.01),
+                f64::max(albedo.z(), 0.01),
+            )
+        };
+        let mut illumination = Image::new(image.width, image.height);
+        for j in 0..image.height {
+            for i in 0..image.width {
+                let color = image.get(i, j);
+                let albedo = albedo(i, j);
+                illumination.set(
+                    i,
+                    j,
+                    Color::new(
+                        color.x() / albedo.x(),
+                        color.y() / albedo.y(),
+                        color.z() / albedo.z(),
+                    ),
+                );
+            }
+        }
+
+        let mut sigma_color = self.strength;
+        for iteration in 0..self.iterations {
+            illumination = self.filter_pass(&illumination, aovs, 1 << iteration, sigma_color);
+            // Later passes see less noise, so they tolerate smaller color differences.
+            sigma_color *= 0.5;
+        }
+
+        for j in 0..image.height {
+            for i in 0..image.width {
+                illumination.set(i, j, illumination.get(i, j) * albedo(i, j));
+            }
+        }
+
+        illumination
+    }
+
+    fn filter_pass(&self, input: &Image, aovs: &AovBuffers, step: i32, sigma_color: f64) -> Image {
+        const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+        let mut output = Image::new(input.width, input.height);
+        for j in 0..input.height {
+            for i in 0..input.width {
+                let color_p = input.get(i, j);
+                let normal_p = aovs.value(Aov::Normal, i, j);
+                let depth_p = aovs.value(Aov::Depth, i, j).x();
+
+                let mut sum = Color::default();
+                let mut weight_sum = 0.0;
+                for (dy, ky) in KERNEL.iter().enumerate() {
+                    for (dx, kx) in KERNEL.iter().enumerate() {
+                        let qi = i + (dx as i32 - 2) * step;
+                        let qj = j + (dy as i32 - 2) * step;
+                        if qi < 0 || qi >= input.width || qj < 0 || qj >= input.height {
+                            continue;
+                        }
+
+                        let color_q = input.get(qi, qj);
+                        let normal_q = aovs.value(Aov::Normal, qi, qj);
+                        let depth_q = aovs.value(Aov::Depth, qi, qj).x();
+
+                        let w_color = f64::exp(
+                            -(color_p - color_q).length_squared() / (sigma_color * sigma_color),
+                        );
+                        let w_normal = f64::exp(
+                            -(normal_p - normal_q).length_squared()
+                                / (self.sigma_normal * self.sigma_normal),
+                        );
+                        let w_depth = f64::exp(
+                            -(depth_p - depth_q).abs()
+                                / (self.sigma_depth * f64::max(depth_p, 1e-3)),
+                        );
+
+                        let weight = kx * ky * w_color * w_normal * w_depth;
+                        sum += weight * color_q;
+                        weight_sum += weight;
+                    }
+                }
+
+                // The center tap always has full weight, so the sum is never zero.
+                output.set(i, j, sum / weight_sum);
+            }
+        }
+
+        output
+    }
+}
@@ -0,0 +1,109 @@
+/// Piecewise-constant 1D distribution over `[0, 1)`, proportional to a tabulated function.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1].abs() / n as f64;
+        }
+
+        // A function that is zero everywhere is sampled uniformly.
+        let func_int = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if func_int == 0.0 {
+                i as f64 / n as f64
+            } else {
+                *c / func_int
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Integral of the function over `[0, 1)`.
+    pub fn integral(&self) -> f64 {
+        self.func_int
+    }
+
+    /// Maps the uniform random number `u` to a sample in `[0, 1)`, returning it with its
+    /// probability density and the index of the segment it falls in.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+        let pdf = if self.func_int > 0.0 {
+            self.func[offset].abs() / self.func_int
+        } else {
+            1.0
+        };
+
+        ((offset as f64 + du) / self.count() as f64, pdf, offset)
+    }
+}
+
+/// Piecewise-constant 2D distribution over `[0, 1)²`, sampled by choosing a row from the
+/// marginal distribution and then a column within it.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Creates the distribution for `func`, given row by row with `nu` values per row.
+    pub fn new(func: &[f64], nu: usize) -> Self {
+        let conditional: Vec<_> = func
+            .chunks_exact(nu)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Maps the uniform random numbers `u0`, `u1` to a sample `(u, v)` and its density.
+    pub fn sample(&self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// Probability density of sampling `(u, v)`.
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        let conditional = &self.conditional[row];
+        let column = ((u * conditional.count() as f64) as usize).min(conditional.count() - 1);
+        if self.marginal.integral() == 0.0 {
+            return 1.0;
+        }
+
+        conditional.func[column].abs() / self.marginal.integral()
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use crate::{distribution::Distribution2D, image::Image, prelude::*};
+
+/// Light arriving from every direction, given by an equirectangular (latitude-longitude) HDR
+/// image laid out like [`Projection::Equirectangular`](crate::projection::Projection), with
+/// -z in the middle and +y at the top. Sampled in proportion to its brightness.
+#[derive(Debug, Clone)]
+pub struct EnvironmentLight {
+    image: Image,
+    distribution: Distribution2D,
+    /// Rotation about the y axis in degrees
+    rotation: f64,
+    /// Scale applied to the radiance of the image
+    intensity: f64,
+}
+
+impl EnvironmentLight {
+    pub fn new(image: Image) -> Self {
+        // Weight by the solid angle each row covers, which shrinks towards the poles.
+        let func: Vec<f64> = (0..image.height)
+            .flat_map(|j| {
+                let sin_theta = f64::sin(PI * (j as f64 + 0.5) / image.height as f64);
+                (0..image.width).map(move |i| (i, j, sin_theta))
+            })
+            .map(|(i, j, sin_theta)| luminance(image.get(i, j)).max(0.0) * sin_theta)
+            .collect();
+        let distribution = Distribution2D::new(&func, image.width as usize);
+
+        Self {
+            image,
+            distribution,
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Loads a Radiance `.hdr` or a `.pfm` image.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let input = std::io::BufReader::new(std::fs::File::open(path)?);
+        let extension = path.extension().unwrap_or_default().to_ascii_lowercase();
+        let image = match extension.to_str() {
+            Some("hdr") | Some("pic") => Image::read_hdr(input)?,
+            Some("pfm") => Image::read_pfm(input)?,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "environment maps must be .hdr or .pfm files",
+                ));
+            }
+        };
+
+        Ok(Self::new(image))
+    }
+
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation;
+
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+
+        self
+    }
+
+    /// Radiance arriving from unit `direction`.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        let i = ((u * self.image.width as f64) as i32).clamp(0, self.image.width - 1);
+        let j = ((v * self.image.height as f64) as i32).clamp(0, self.image.height - 1);
+
+        self.intensity * self.image.get(i, j)
+    }
+
+    /// Samples a direction towards the environment, returning it with the radiance from it
+    /// and its probability density over solid angle, or `None` if the environment is black.
+    pub fn sample(&self) -> Option<(Vec3, Color, f64)> {
+        let ((u, v), map_pdf) = self.distribution.sample(rng::random(), rng::random());
+        let cos_latitude = f64::sin(PI * v);
+        if map_pdf == 0.0 || cos_latitude == 0.0 {
+            return None;
+        }
+
+        let direction = self.uv_to_direction(u, v);
+        let pdf = map_pdf / (2.0 * PI * PI * cos_latitude);
+
+        Some((direction, self.radiance(direction), pdf))
+    }
+
+    /// Probability density over solid angle of [`EnvironmentLight::sample`] returning unit
+    /// `direction`.
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let cos_latitude = f64::sin(PI * v);
+        if cos_latitude == 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * cos_latitude)
+    }
+
+    fn direction_to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let phi = f64::atan2(direction.x(), -direction.z()) - self.rotation.to_radians();
+        let latitude = f64::asin(direction.y().clamp(-1.0, 1.0));
+
+        (
+            (phi / (2.0 * PI) + 0.5).rem_euclid(1.0),
+            0.5 - latitude / PI,
+        )
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let latitude = (0.5 - v) * PI;
+
+        Vec3::new(
+            f64::cos(latitude) * f64::sin(phi),
+            f64::sin(latitude),
+            -f64::cos(latitude) * f64::cos(phi),
+        )
+    }
+}
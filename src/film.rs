@@ -0,0 +1,209 @@
+use crate::{
+    aov::AovBuffers,
+    checkpoint::{read_f64, read_i32, write_f64, write_i32},
+    filter::Filter,
+    image::Image,
+    prelude::*,
+    tonemap::ToneMapping,
+};
+
+/// Accumulation buffer holding the filter-weighted sum of samples for every pixel.
+#[derive(Debug, Clone)]
+pub struct Film {
+    pub width: i32,
+    pub height: i32,
+    filter: Filter,
+    /// Weighted sum of sample colors per pixel
+    sum: Vec<Color>,
+    /// Sum of filter weights per pixel
+    weight: Vec<f64>,
+    /// Number of samples taken for each pixel
+    samples: Vec<i32>,
+    /// Sum of the luminance of the samples taken for each pixel
+    luminance_sum: Vec<f64>,
+    /// Sum of the squared luminance of the samples taken for each pixel
+    luminance_sq_sum: Vec<f64>,
+    /// Auxiliary output buffers, if any were requested
+    pub aovs: Option<AovBuffers>,
+}
+
+impl Film {
+    pub fn new(width: i32, height: i32, filter: Filter) -> Self {
+        let len = (width * height) as usize;
+        Self {
+            width,
+            height,
+            filter,
+            sum: vec![Color::default(); len],
+            weight: vec![0.0; len],
+            samples: vec![0; len],
+            luminance_sum: vec![0.0; len],
+            luminance_sq_sum: vec![0.0; len],
+            aovs: None,
+        }
+    }
+
+    /// Adds auxiliary output buffers to the film.
+    pub fn with_aovs(mut self) -> Self {
+        self.aovs = Some(AovBuffers::new(self.width, self.height));
+
+        self
+    }
+
+    /// Records a sample taken for pixel `i`, `j` at `offset` from its center, and splats it into
+    /// every pixel within the filter radius.
+    pub fn add_sample(&mut self, i: i32, j: i32, offset: Vec3, color: Color) {
+        let index = self.index(i, j);
+        let l = luminance(color);
+        self.samples[index] += 1;
+        self.luminance_sum[index] += l;
+        self.luminance_sq_sum[index] += l * l;
+
+        self.splat(i as f64 + offset.x(), j as f64 + offset.y(), color);
+    }
+
+    /// Returns the number of samples taken for pixel `i`, `j`.
+    pub fn samples(&self, i: i32, j: i32) -> i32 {
+        self.samples[self.index(i, j)]
+    }
+
+    /// Returns the estimated standard error of the gamma-encoded luminance of pixel `i`, `j`.
+    pub fn error(&self, i: i32, j: i32) -> f64 {
+        let index = self.index(i, j);
+        let n = self.samples[index] as f64;
+        if n < 2.0 {
+            return INFINITY;
+        }
+
+        let mean = self.luminance_sum[index] / n;
+        let variance = f64::max(
+            0.0,
+            (self.luminance_sq_sum[index] - n * mean * mean) / (n - 1.0),
+        );
+        let standard_error = f64::sqrt(variance / n);
+
+        // Propagate the error through the gamma 2 transform, d(sqrt(x))/dx = 1 / (2 sqrt(x)).
+        standard_error / (2.0 * f64::sqrt(f64::max(mean, 1e-4)))
+    }
+
+    /// Splats a sample at film position `x`, `y` (pixel centers lie on integer coordinates)
+    /// into every pixel within the filter radius.
+    fn splat(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        let i0 = i32::max(0, (x - radius).ceil() as i32);
+        let i1 = i32::min(self.width - 1, (x + radius).floor() as i32);
+        let j0 = i32::max(0, (y - radius).ceil() as i32);
+        let j1 = i32::min(self.height - 1, (y + radius).floor() as i32);
+
+        for j in j0..=j1 {
+            for i in i0..=i1 {
+                let weight = self.filter.evaluate(i as f64 - x, j as f64 - y);
+                if weight == 0.0 {
+                    continue;
+                }
+                let index = self.index(i, j);
+                self.sum[index] += weight * color;
+                self.weight[index] += weight;
+            }
+        }
+    }
+
+    /// Returns the reconstructed color of pixel `i`, `j`.
+    pub fn pixel(&self, i: i32, j: i32) -> Color {
+        let index = self.index(i, j);
+        if self.weight[index] == 0.0 {
+            Color::default()
+        } else {
+            self.sum[index] / self.weight[index]
+        }
+    }
+
+    /// Returns the reconstructed image.
+    pub fn image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                image.set(i, j, self.pixel(i, j));
+            }
+        }
+
+        image
+    }
+
+    /// Writes the reconstructed image as a plain PPM.
+    pub fn write_ppm(
+        &self,
+        out: impl std::io::Write,
+        tone_mapping: &ToneMapping,
+    ) -> std::io::Result<()> {
+        self.image().write_ppm(out, tone_mapping)
+    }
+
+    /// Writes the per-pixel sample counts as a grayscale PPM, scaled so the most sampled pixel
+    /// is white.
+    pub fn write_sample_count_map(&self, mut out: impl std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+
+        let max_samples = self.samples.iter().copied().max().unwrap_or(0).max(1);
+        for &samples in &self.samples {
+            let byte = 255 * samples / max_samples;
+            writeln!(out, "{byte} {byte} {byte}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the raw accumulation buffers, for checkpointing.
+    pub(crate) fn write_state(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        write_i32(out, self.width)?;
+        write_i32(out, self.height)?;
+        for index in 0..self.sum.len() {
+            for c in self.sum[index].e {
+                write_f64(out, c)?;
+            }
+            write_f64(out, self.weight[index])?;
+            write_i32(out, self.samples[index])?;
+            write_f64(out, self.luminance_sum[index])?;
+            write_f64(out, self.luminance_sq_sum[index])?;
+        }
+        match &self.aovs {
+            Some(aovs) => {
+                write_i32(out, 1)?;
+                aovs.write_state(out)?;
+            }
+            None => write_i32(out, 0)?,
+        }
+
+        Ok(())
+    }
+
+    /// Reads accumulation buffers written by [`Film::write_state`].
+    pub(crate) fn read_state(
+        input: &mut impl std::io::Read,
+        filter: Filter,
+    ) -> std::io::Result<Self> {
+        let width = read_i32(input)?;
+        let height = read_i32(input)?;
+        let mut film = Self::new(width, height, filter);
+        for index in 0..film.sum.len() {
+            for c in &mut film.sum[index].e {
+                *c = read_f64(input)?;
+            }
+            film.weight[index] = read_f64(input)?;
+            film.samples[index] = read_i32(input)?;
+            film.luminance_sum[index] = read_f64(input)?;
+            film.luminance_sq_sum[index] = read_f64(input)?;
+        }
+        if read_i32(input)? != 0 {
+            film.aovs = Some(AovBuffers::read_state(input, width, height)?);
+        }
+
+        Ok(film)
+    }
+
+    fn index(&self, i: i32, j: i32) -> usize {
+        (j * self.width + i) as usize
+    }
+}
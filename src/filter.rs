@@ -0,0 +1,95 @@
+use crate::prelude::*;
+
+/// Pixel reconstruction filter used to weight each sample into the pixels around it.
+///
+/// All filters are separable: the 2D weight is the product of the 1D weights along x and y.
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    /// Constant weight over `[-radius, radius]`
+    Box { radius: f64 },
+    /// Linear falloff from the center to zero at `radius`
+    Tent { radius: f64 },
+    /// Gaussian of falloff `alpha`, shifted so it reaches zero at `radius`
+    Gaussian { radius: f64, alpha: f64 },
+    /// Mitchell-Netravali cubic with parameters `b` and `c`
+    Mitchell { radius: f64, b: f64, c: f64 },
+    /// Sinc windowed by a wider sinc stretched by `tau`
+    Lanczos { radius: f64, tau: f64 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// Returns the weight of a sample at offset `x`, `y` (in pixels) from a pixel center.
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    /// Returns a random sample offset in the square covered by the filter.
+    pub fn sample_offset(&self) -> Vec3 {
+        // Never sample a smaller area than the pixel itself, so narrow filters still cover it.
+        let half_width = f64::max(self.radius(), 0.5);
+        Vec3::new(
+            rng::random_range(-half_width..half_width),
+            rng::random_range(-half_width..half_width),
+            0.0,
+        )
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { radius } => radius - x,
+            Self::Gaussian { radius, alpha } => f64::max(
+                0.0,
+                (-alpha * x * x).exp() - (-alpha * radius * radius).exp(),
+            ),
+            Self::Mitchell { radius, b, c } => {
+                // The cubic is defined over [-2, 2], so remap the filter radius onto it.
+                let x = 2.0 * x / radius;
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Self::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    let x = x.abs();
+    if x < 1e-5 {
+        1.0
+    } else {
+        f64::sin(PI * x) / (PI * x)
+    }
+}
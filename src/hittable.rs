@@ -1,4 +1,5 @@
 use crate::{
+    aabb::Aabb,
     material::{Lambertian, Material},
     prelude::*,
 };
@@ -9,7 +10,14 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub mat: Rc<dyn Material>,
     pub t: f64,
+    /// Surface coordinates for texture lookups
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    /// Index of the top-level object that was hit, starting from 1
+    pub object_id: u32,
+    /// Refractive index of the medium on the other side of the surface from the object hit
+    pub outer_ior: f64,
 }
 
 impl Default for HitRecord {
@@ -19,7 +27,11 @@ impl Default for HitRecord {
             normal: Default::default(),
             mat: Rc::new(Lambertian::default()),
             t: Default::default(),
+            u: Default::default(),
+            v: Default::default(),
             front_face: Default::default(),
+            object_id: Default::default(),
+            outer_ior: 1.0,
         }
     }
 }
@@ -38,6 +50,9 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: std::fmt::Debug {
     fn hit(&self, r: Ray, ray_t: Interval) -> Option<HitRecord>;
+
+    /// Box enclosing the object, or [`Aabb::EMPTY`] if there is nothing to enclose.
+    fn bounding_box(&self) -> Aabb;
 }
@@ -1,9 +1,10 @@
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     prelude::*,
 };
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct HittableList {
     pub objects: Vec<Rc<dyn Hittable>>,
 }
@@ -26,7 +27,19 @@ impl Hittable for HittableList {
     fn hit(&self, r: Ray, ray_t: Interval) -> Option<HitRecord> {
         self.objects
             .iter()
-            .filter_map(|obj| obj.hit(r, ray_t))
+            .enumerate()
+            .filter_map(|(index, obj)| {
+                obj.hit(r, ray_t).map(|rec| HitRecord {
+                    object_id: index as u32 + 1,
+                    ..rec
+                })
+            })
             .min_by(|a, b| a.t.partial_cmp(&b.t).expect("no NaN value"))
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |bbox, obj| {
+            Aabb::surrounding(&bbox, &obj.bounding_box())
+        })
+    }
 }
@@ -0,0 +1,293 @@
+use crate::{prelude::*, tonemap::ToneMapping};
+
+/// Linear floating point RGB image.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: i32,
+    pub height: i32,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, i: i32, j: i32) -> Color {
+        self.pixels[(j * self.width + i) as usize]
+    }
+
+    pub fn set(&mut self, i: i32, j: i32, color: Color) {
+        self.pixels[(j * self.width + i) as usize] = color;
+    }
+
+    /// Reads a plain (P3) or binary (P6) PPM. Values are scaled to `[0, 1]` but otherwise
+    /// taken as stored, without undoing any transfer function.
+    pub fn read_ppm(mut input: impl std::io::Read) -> std::io::Result<Self> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+
+        let mut pos = 0;
+        let next_number = |pos: &mut usize| -> std::io::Result<i32> {
+            next_token(&data, pos)
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid("malformed PPM header"))
+        };
+
+        let magic = next_token(&data, &mut pos).unwrap_or_default();
+        let width = next_number(&mut pos)?;
+        let height = next_number(&mut pos)?;
+        let max_value = next_number(&mut pos)?;
+        if width <= 0 || height <= 0 || !(1..=255).contains(&max_value) {
+            return Err(invalid("unsupported PPM dimensions or depth"));
+        }
+        pixel_count(width, height)?;
+
+        let mut image = Self::new(width, height);
+        let scale = 1.0 / max_value as f64;
+        match magic.as_str() {
+            "P3" => {
+                for pixel in &mut image.pixels {
+                    for c in &mut pixel.e {
+                        *c = scale * next_number(&mut pos)? as f64;
+                    }
+                }
+            }
+            "P6" => {
+                // A single whitespace byte separates the header from the samples.
+                let samples = data
+                    .get(pos + 1..pos + 1 + 3 * image.pixels.len())
+                    .ok_or_else(|| invalid("truncated PPM data"))?;
+                for (pixel, rgb) in image.pixels.iter_mut().zip(samples.chunks_exact(3)) {
+                    *pixel = scale * Color::new(rgb[0] as f64, rgb[1] as f64, rgb[2] as f64);
+                }
+            }
+            _ => return Err(invalid("not a PPM image")),
+        }
+
+        Ok(image)
+    }
+
+    /// Reads a color (`PF`) or grayscale (`Pf`) PFM (portable float map) with linear values.
+    pub fn read_pfm(mut input: impl std::io::Read) -> std::io::Result<Self> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+
+        let mut pos = 0;
+        let magic = next_token(&data, &mut pos).unwrap_or_default();
+        let channels = match magic.as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid("not a PFM image")),
+        };
+        let mut next_number = || -> std::io::Result<f64> {
+            next_token(&data, &mut pos)
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid("malformed PFM header"))
+        };
+        let width = next_number()? as i32;
+        let height = next_number()? as i32;
+        // The sign of the scale gives the byte order, negative for little-endian.
+        let little_endian = next_number()? < 0.0;
+        if width <= 0 || height <= 0 {
+            return Err(invalid("unsupported PFM dimensions"));
+        }
+
+        let start = pos + 1;
+        let len = 4 * channels * pixel_count(width, height)?;
+        let samples = data
+            .get(start..start + len)
+            .ok_or_else(|| invalid("truncated PFM data"))?;
+        let values: Vec<f64> = samples
+            .chunks_exact(4)
+            .map(|bytes| {
+                let bytes = bytes.try_into().expect("four bytes");
+                if little_endian {
+                    f32::from_le_bytes(bytes) as f64
+                } else {
+                    f32::from_be_bytes(bytes) as f64
+                }
+            })
+            .collect();
+
+        // PFM scanlines are stored bottom to top.
+        let mut image = Self::new(width, height);
+        for (row, pixels) in values.chunks_exact(channels * width as usize).enumerate() {
+            let j = height - 1 - row as i32;
+            for (i, pixel) in pixels.chunks_exact(channels).enumerate() {
+                let color = match pixel {
+                    [r, g, b] => Color::new(*r, *g, *b),
+                    [v] => Color::new(*v, *v, *v),
+                    _ => unreachable!(),
+                };
+                image.set(i as i32, j, color);
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// Reads a Radiance RGBE (`.hdr`) image, flat or run-length encoded, with linear values.
+    pub fn read_hdr(mut input: impl std::io::Read) -> std::io::Result<Self> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+
+        // The header is a block of text lines ended by an empty line, followed by the
+        // resolution line.
+        let mut lines = data.split(|&b| b == b'\n');
+        let mut pos = 0;
+        let mut next_line = || {
+            let line = lines.next()?;
+            pos += line.len() + 1;
+            Some(String::from_utf8_lossy(line).into_owned())
+        };
+        let magic = next_line().unwrap_or_default();
+        if !magic.starts_with("#?") {
+            return Err(invalid("not a Radiance HDR image"));
+        }
+        loop {
+            match next_line() {
+                None => return Err(invalid("truncated HDR header")),
+                Some(line) if line.trim().is_empty() => break,
+                Some(line) => {
+                    if let Some(format) = line.strip_prefix("FORMAT=")
+                        && format.trim() != "32-bit_rle_rgbe"
+                    {
+                        return Err(invalid("unsupported HDR pixel format"));
+                    }
+                }
+            }
+        }
+        let resolution = next_line().unwrap_or_default();
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (height.parse().ok(), width.parse().ok()),
+            _ => return Err(invalid("unsupported HDR orientation")),
+        };
+        let (Some(height), Some(width)) = (height, width) else {
+            return Err(invalid("malformed HDR resolution"));
+        };
+        if width <= 0 || height <= 0 {
+            return Err(invalid("unsupported HDR dimensions"));
+        }
+        pixel_count(width, height)?;
+
+        let mut image = Self::new(width, height);
+        let mut bytes = data.get(pos..).unwrap_or_default().iter().copied();
+        let mut next_byte = || bytes.next().ok_or_else(|| invalid("truncated HDR data"));
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for j in 0..height {
+            let header = [next_byte()?, next_byte()?, next_byte()?, next_byte()?];
+            let run_length_encoded = (8..0x8000).contains(&width)
+                && header[0] == 2
+                && header[1] == 2
+                && header[2] < 0x80;
+            if run_length_encoded {
+                if ((header[2] as i32) << 8 | header[3] as i32) != width {
+                    return Err(invalid("HDR scanline width mismatch"));
+                }
+                // Each component is stored separately as runs and literal spans.
+                for component in 0..4 {
+                    let mut i = 0;
+                    while i < scanline.len() {
+                        let count = next_byte()? as usize;
+                        let (len, run_value) = match count {
+                            0 => return Err(invalid("empty HDR run")),
+                            1..=128 => (count, None),
+                            _ => (count - 128, Some(next_byte()?)),
+                        };
+                        if i + len > scanline.len() {
+                            return Err(invalid("HDR run overflows scanline"));
+                        }
+                        for rgbe in &mut scanline[i..i + len] {
+                            rgbe[component] = match run_value {
+                                Some(value) => value,
+                                None => next_byte()?,
+                            };
+                        }
+                        i += len;
+                    }
+                }
+            } else {
+                scanline[0] = header;
+                for rgbe in &mut scanline[1..] {
+                    *rgbe = [next_byte()?, next_byte()?, next_byte()?, next_byte()?];
+                }
+            }
+
+            for (i, &[r, g, b, e]) in scanline.iter().enumerate() {
+                let color = if e == 0 {
+                    Color::default()
+                } else {
+                    let f = f64::powi(2.0, e as i32 - (128 + 8));
+                    Color::new(r as f64 * f, g as f64 * f, b as f64 * f)
+                };
+                image.set(i as i32, j, color);
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// Writes the image as a plain PPM, converted to low dynamic range with `tone_mapping`.
+    pub fn write_ppm(
+        &self,
+        mut out: impl std::io::Write,
+        tone_mapping: &ToneMapping,
+    ) -> std::io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+
+        for &pixel_color in &self.pixels {
+            write_color(&mut out, pixel_color, tone_mapping)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of pixels of a `width` by `height` image read from a file, or an error
+/// if there are too many to index.
+fn pixel_count(width: i32, height: i32) -> std::io::Result<usize> {
+    width
+        .checked_mul(height)
+        .map(|count| count as usize)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "image dimensions are too large",
+            )
+        })
+}
+
+/// Returns the next whitespace separated token of an image header, skipping `#` comments.
+fn next_token(data: &[u8], pos: &mut usize) -> Option<String> {
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < data.len() && data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            continue;
+        }
+        break;
+    }
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+
+    (start < *pos).then(|| String::from_utf8_lossy(&data[start..*pos]).into_owned())
+}
@@ -25,6 +25,11 @@ impl Interval {
         Self { min, max }
     }
 
+    /// Smallest interval containing both `a` and `b`.
+    pub fn surrounding(a: &Interval, b: &Interval) -> Self {
+        Self::new(f64::min(a.min, b.min), f64::max(a.max, b.max))
+    }
+
     pub fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -0,0 +1,453 @@
+use std::path::Path;
+
+use crate::{prelude::*, spectrum::Dispersion};
+
+/// Number of rings the film is divided into for the exit pupil bounds.
+const PUPIL_BINS: usize = 32;
+/// Grid resolution per side when searching for the exit pupil of a ring.
+const PUPIL_GRID: usize = 64;
+
+/// One refracting surface of a lens prescription, or the aperture stop. Lengths are in
+/// millimeters.
+#[derive(Debug, Clone, Copy)]
+pub struct LensElement {
+    /// Radius of curvature, positive when the center of curvature lies towards the film; 0 for
+    /// the aperture stop
+    pub radius: f64,
+    /// Distance along the axis to the next surface towards the film
+    pub thickness: f64,
+    /// Refractive index at the helium d line (587.6 nm) of the medium behind the surface, where
+    /// 0 or 1 is air
+    pub ior: f64,
+    /// Diameter of the clear aperture
+    pub aperture: f64,
+    /// Abbe number of the medium behind the surface, or 0 for no dispersion
+    pub abbe: f64,
+}
+
+impl LensElement {
+    /// Refractive index of the medium behind the surface at `wavelength` nanometers, or at
+    /// the d line if `None`.
+    fn ior_at(&self, wavelength: Option<f64>) -> f64 {
+        if self.ior == 0.0 {
+            return 1.0;
+        }
+        match wavelength {
+            Some(wavelength) if self.abbe > 0.0 => {
+                Dispersion::from_abbe(self.ior, self.abbe).ior(wavelength)
+            }
+            _ => self.ior,
+        }
+    }
+
+    fn is_stop(&self) -> bool {
+        self.radius == 0.0
+    }
+}
+
+/// Multi-element spherical lens traced surface by surface from the film into the scene. The
+/// lens is focused by moving it relative to the film, and brightness is normalized to the
+/// center of the frame, so only the falloff towards the edges changes the exposure.
+#[derive(Debug, Clone)]
+pub struct Lens {
+    /// Surfaces from the front (scene side) to the rear (film side)
+    pub elements: Vec<LensElement>,
+    /// Width of the film in millimeters
+    pub sensor_width: f64,
+    /// Length of one scene unit in meters
+    pub meters_per_unit: f64,
+
+    /// Height of the film in millimeters
+    sensor_height: f64,
+    /// Distance from the film to the rear surface
+    film_distance: f64,
+    /// Bounds `[x0, x1, y0, y1]` of the rear surface region that light passes through, for
+    /// film points on the positive x axis in each ring
+    pupil_bounds: Vec<[f64; 4]>,
+    /// Area of the exit pupil seen from the film center
+    center_pupil_area: f64,
+}
+
+impl Lens {
+    /// Returns an error if `elements` is empty.
+    pub fn new(elements: Vec<LensElement>) -> std::io::Result<Self> {
+        let Some(rear) = elements.last() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "lens prescription has no surfaces",
+            ));
+        };
+        let film_distance = rear.thickness;
+
+        Ok(Self {
+            elements,
+            sensor_width: 36.0,
+            meters_per_unit: 1.0,
+            sensor_height: 24.0,
+            film_distance,
+            pupil_bounds: Vec::new(),
+            center_pupil_area: 0.0,
+        })
+    }
+
+    /// Reads a lens prescription: one surface per line, front to rear, as whitespace separated
+    /// radius, thickness, index of refraction, aperture diameter and an optional Abbe number,
+    /// all lengths in millimeters. A radius of 0 marks the aperture stop. Text after `#` is
+    /// ignored.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let mut elements = Vec::new();
+        for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| invalid(format!("line {}: {err}", number + 1)))?;
+
+            match values[..] {
+                [] => continue,
+                [radius, thickness, ior, aperture] | [radius, thickness, ior, aperture, _] => {
+                    elements.push(LensElement {
+                        radius,
+                        thickness,
+                        ior,
+                        aperture,
+                        abbe: values.get(4).copied().unwrap_or(0.0),
+                    })
+                }
+                _ => {
+                    return Err(invalid(format!(
+                        "line {}: expected 4 or 5 values",
+                        number + 1
+                    )));
+                }
+            }
+        }
+
+        Self::new(elements)
+    }
+
+    pub fn with_sensor_width(mut self, sensor_width: f64) -> Self {
+        self.sensor_width = sensor_width;
+
+        self
+    }
+
+    pub fn with_meters_per_unit(mut self, meters_per_unit: f64) -> Self {
+        self.meters_per_unit = meters_per_unit;
+
+        self
+    }
+
+    /// Whether any element has a wavelength dependent index of refraction.
+    pub fn is_dispersive(&self) -> bool {
+        self.elements
+            .iter()
+            .any(|element| element.ior > 1.0 && element.abbe > 0.0)
+    }
+
+    /// Moves the lens to focus at `focus_dist` scene units in front of the film, and prepares
+    /// sampling for a film of the given aspect ratio.
+    pub fn focus(&mut self, focus_dist: f64, aspect_ratio: f64) {
+        self.sensor_height = self.sensor_width / aspect_ratio;
+        self.film_distance = 0.0;
+
+        // Cardinal points of the thick lens approximation, relative to the rear surface, from
+        // paraxial rays traced through the lens in both directions.
+        let height = 0.001 * self.film_radius();
+        let (image_focal_z, image_principal_z) = self
+            .trace_from_scene(
+                Vec3::new(height, 0.0, self.front_z() + 1.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            )
+            .map_or((0.0, 0.0), |(o, d)| cardinal_points(height, o, d));
+        let (_, object_principal_z) = self
+            .trace_from_film(Vec3::new(height, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), None)
+            .map_or((0.0, 0.0), |(o, d)| cardinal_points(height, o, d));
+        let focal_length = image_principal_z - image_focal_z;
+
+        // Solve the lens equation for the image distance `a` from the rear principal plane.
+        let focus_dist = 1000.0 * self.meters_per_unit * focus_dist;
+        let k = focus_dist - object_principal_z + image_principal_z;
+        let discriminant = k * k - 4.0 * focal_length * k;
+        let a = if focal_length <= 0.0 || discriminant < 0.0 {
+            warn!("The lens cannot focus at {focus_dist} mm, focusing as close as it can.");
+            f64::max(k / 2.0, 0.0)
+        } else {
+            (k - f64::sqrt(discriminant)) / 2.0
+        };
+        self.film_distance = a - image_principal_z;
+        info!(
+            "Lens focal length {focal_length:.1} mm, film {:.2} mm behind the rear element.",
+            self.film_distance
+        );
+
+        self.bound_exit_pupil();
+    }
+
+    /// Returns the origin, direction and radiometric weight of a ray through image position
+    /// `s`, `t`, where both range over `[0, 1]` from the upper left corner, in the camera frame
+    /// (x right, y up, z opposite the view direction) and scene units. Returns `None` if the
+    /// sampled ray is blocked inside the lens, or if the lens has not been focused with
+    /// [`Lens::focus`] yet. Dispersive lenses refract at `wavelength` nanometers, or at the d
+    /// line if `None`.
+    pub fn camera_ray(&self, s: f64, t: f64, wavelength: Option<f64>) -> Option<(Vec3, Vec3, f64)> {
+        if self.pupil_bounds.is_empty() {
+            return None;
+        }
+
+        // The lens forms an inverted image, so the film point is mirrored.
+        let film = Vec3::new(
+            (s - 0.5) * -self.sensor_width,
+            (t - 0.5) * self.sensor_height,
+            0.0,
+        );
+        let (rear, pupil_area) = self.sample_exit_pupil(film);
+
+        let direction = unit_vector(rear - film);
+        let (origin, out) = self.trace_from_film(film, direction, wavelength)?;
+
+        // Irradiance falls off with the fourth power of the cosine to the axis.
+        let cos_theta = direction.z();
+        let weight = cos_theta.powi(4) * pupil_area / self.center_pupil_area;
+
+        let scale = 0.001 / self.meters_per_unit;
+        Some((
+            Vec3::new(scale * origin.x(), scale * origin.y(), -scale * origin.z()),
+            Vec3::new(out.x(), out.y(), -out.z()),
+            weight,
+        ))
+    }
+
+    fn film_radius(&self) -> f64 {
+        f64::hypot(self.sensor_width, self.sensor_height) / 2.0
+    }
+
+    /// Axial position of the surface `index`, with the film at 0 and the scene towards +z.
+    fn vertex_z(&self, index: usize) -> f64 {
+        let last = self.elements.len() - 1;
+        self.film_distance
+            + self.elements[index..last]
+                .iter()
+                .map(|element| element.thickness)
+                .sum::<f64>()
+    }
+
+    fn front_z(&self) -> f64 {
+        self.vertex_z(0)
+    }
+
+    fn rear_radius(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.aperture / 2.0)
+    }
+
+    /// Traces a ray from the film side out of the front of the lens, returning the exit point
+    /// and direction, or `None` if an element blocks it.
+    fn trace_from_film(
+        &self,
+        mut origin: Vec3,
+        mut direction: Vec3,
+        wavelength: Option<f64>,
+    ) -> Option<(Vec3, Vec3)> {
+        for index in (0..self.elements.len()).rev() {
+            let element = &self.elements[index];
+            let (p, normal) = self.intersect(index, origin, direction)?;
+            origin = p;
+
+            if !element.is_stop() {
+                let eta_i = element.ior_at(wavelength);
+                let eta_t = match index {
+                    0 => 1.0,
+                    _ => self.elements[index - 1].ior_at(wavelength),
+                };
+                direction = refract_checked(direction, normal, eta_i / eta_t)?;
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    /// Traces a ray from the scene side out of the rear of the lens, like
+    /// [`Lens::trace_from_film`] in reverse.
+    fn trace_from_scene(&self, mut origin: Vec3, mut direction: Vec3) -> Option<(Vec3, Vec3)> {
+        for index in 0..self.elements.len() {
+            let element = &self.elements[index];
+            let (p, normal) = self.intersect(index, origin, direction)?;
+            origin = p;
+
+            if !element.is_stop() {
+                let eta_i = match index {
+                    0 => 1.0,
+                    _ => self.elements[index - 1].ior_at(None),
+                };
+                let eta_t = element.ior_at(None);
+                direction = refract_checked(direction, normal, eta_i / eta_t)?;
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    /// Intersects surface `index`, returning the hit point and the normal facing the incoming
+    /// ray, or `None` if the ray misses the surface or passes outside its aperture.
+    fn intersect(&self, index: usize, origin: Vec3, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        let element = &self.elements[index];
+        let z = self.vertex_z(index);
+
+        let (t, normal) = if element.is_stop() {
+            let t = (z - origin.z()) / direction.z();
+            (t, Vec3::new(0.0, 0.0, -direction.z().signum()))
+        } else {
+            let center = Vec3::new(0.0, 0.0, z - element.radius);
+            let oc = origin - center;
+            let a = direction.length_squared();
+            let h = dot(direction, oc);
+            let c = oc.length_squared() - element.radius * element.radius;
+            let discriminant = h * h - a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let sqrtd = f64::sqrt(discriminant);
+
+            // The surface is the half of the sphere around the vertex; which intersection that
+            // is depends on the travel direction and the side of the center.
+            let use_closer = (direction.z() < 0.0) != (element.radius < 0.0);
+            let t = if use_closer {
+                (-h - sqrtd) / a
+            } else {
+                (-h + sqrtd) / a
+            };
+            let normal = unit_vector(oc + t * direction);
+            let normal = if dot(normal, direction) > 0.0 {
+                -normal
+            } else {
+                normal
+            };
+            (t, normal)
+        };
+        if t < 0.0 {
+            return None;
+        }
+
+        let p = origin + t * direction;
+        let radius = element.aperture / 2.0;
+        if p.x() * p.x() + p.y() * p.y() > radius * radius {
+            return None;
+        }
+
+        Some((p, normal))
+    }
+
+    /// Finds, for rings of film points, the part of the rear surface that rays reaching the
+    /// scene pass through, so sampling can skip the rest.
+    fn bound_exit_pupil(&mut self) {
+        let rear_z = self.vertex_z(self.elements.len() - 1);
+        let extent = 1.5 * self.rear_radius();
+        let cell = 2.0 * extent / PUPIL_GRID as f64;
+
+        let film_radius = self.film_radius();
+        let mut center_passes = 0;
+        self.pupil_bounds = (0..PUPIL_BINS)
+            .map(|bin| {
+                let mut bounds = [INFINITY, -INFINITY, INFINITY, -INFINITY];
+                for (edge, x) in [bin, bin + 1].into_iter().enumerate() {
+                    let film = Vec3::new(film_radius * x as f64 / PUPIL_BINS as f64, 0.0, 0.0);
+                    for gi in 0..PUPIL_GRID {
+                        for gj in 0..PUPIL_GRID {
+                            let rear = Vec3::new(
+                                -extent + (gi as f64 + 0.5) * cell,
+                                -extent + (gj as f64 + 0.5) * cell,
+                                rear_z,
+                            );
+                            if self
+                                .trace_from_film(film, unit_vector(rear - film), None)
+                                .is_none()
+                            {
+                                continue;
+                            }
+                            if bin == 0 && edge == 0 {
+                                center_passes += 1;
+                            }
+                            bounds[0] = f64::min(bounds[0], rear.x());
+                            bounds[1] = f64::max(bounds[1], rear.x());
+                            bounds[2] = f64::min(bounds[2], rear.y());
+                            bounds[3] = f64::max(bounds[3], rear.y());
+                        }
+                    }
+                }
+
+                if bounds[0] > bounds[1] {
+                    [-extent, extent, -extent, extent]
+                } else {
+                    [
+                        bounds[0] - cell,
+                        bounds[1] + cell,
+                        bounds[2] - cell,
+                        bounds[3] + cell,
+                    ]
+                }
+            })
+            .collect();
+
+        self.center_pupil_area = f64::max(center_passes as f64, 1.0) * cell * cell;
+        if center_passes == 0 {
+            warn!("No light passes through the lens to the film center.");
+        }
+    }
+
+    /// Returns a random point on the rear surface's plane within the exit pupil bounds for
+    /// `film`, and the area of the bounds.
+    fn sample_exit_pupil(&self, film: Vec3) -> (Vec3, f64) {
+        let r = f64::hypot(film.x(), film.y());
+        let bin = ((r / self.film_radius() * PUPIL_BINS as f64) as usize).min(PUPIL_BINS - 1);
+        let [x0, x1, y0, y1] = self.pupil_bounds[bin];
+
+        let x = x0 + (x1 - x0) * rng::random::<f64>();
+        let y = y0 + (y1 - y0) * rng::random::<f64>();
+
+        // The bounds were found on the positive x axis; rotate them around to the film point.
+        let (sin_phi, cos_phi) = if r > 0.0 {
+            (film.y() / r, film.x() / r)
+        } else {
+            (0.0, 1.0)
+        };
+        let rear = Vec3::new(
+            cos_phi * x - sin_phi * y,
+            sin_phi * x + cos_phi * y,
+            self.vertex_z(self.elements.len() - 1),
+        );
+
+        (rear, (x1 - x0) * (y1 - y0))
+    }
+}
+
+/// Returns the axial positions where a paraxial ray that entered parallel to the axis at
+/// `height` and left at `origin` in `direction` crosses the axis (the focal point) and its
+/// original height (the principal plane).
+fn cardinal_points(height: f64, origin: Vec3, direction: Vec3) -> (f64, f64) {
+    let focal_t = -origin.x() / direction.x();
+    let principal_t = (height - origin.x()) / direction.x();
+
+    (
+        origin.z() + focal_t * direction.z(),
+        origin.z() + principal_t * direction.z(),
+    )
+}
+
+/// Refracts the unit vector `direction` at a surface with unit `normal` facing against it.
+/// Returns `None` on total internal reflection, which blocks the ray.
+fn refract_checked(direction: Vec3, normal: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = -dot(direction, normal);
+    let sin2_t = eta * eta * f64::max(0.0, 1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = f64::sqrt(1.0 - sin2_t);
+
+    Some(eta * direction + (eta * cos_i - cos_t) * normal)
+}
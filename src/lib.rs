@@ -1,10 +1,40 @@
+pub mod aabb;
+pub mod adaptive;
+pub mod animation;
+pub mod aov;
+pub mod aperture;
+pub mod autofocus;
+pub mod background;
 pub mod camera;
+pub mod checkpoint;
 pub mod color;
+pub mod denoise;
+pub mod distribution;
+pub mod environment;
+pub mod film;
+pub mod filter;
 pub mod hittable;
 pub mod hittable_list;
+pub mod image;
 pub mod interval;
+pub mod lens;
+pub mod light;
+pub mod light_list;
 pub mod material;
+pub mod medium;
+pub mod onb;
+pub mod physical_camera;
 pub mod prelude;
+pub mod progressive;
+pub mod projection;
 pub mod ray;
+pub mod rng;
+pub mod sky;
+pub mod spectrum;
 pub mod sphere;
+pub mod stats;
+pub mod stereo;
+pub mod texture;
+pub mod thin_film;
+pub mod tonemap;
 pub mod vec3;
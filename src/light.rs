@@ -0,0 +1,192 @@
+use crate::{
+    onb::{self, Onb},
+    prelude::*,
+};
+
+/// Light arriving at a point from a light source.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit direction from the lit point towards the light
+    pub direction: Vec3,
+    /// Distance to the light, infinite for lights at infinity
+    pub distance: f64,
+    /// Irradiance on a surface facing the light
+    pub irradiance: Color,
+}
+
+/// Light source that is sampled explicitly rather than found by scattered rays.
+pub trait Light: std::fmt::Debug {
+    /// Samples the light arriving at `p`, or returns `None` if none does.
+    fn sample(&self, p: Point3) -> Option<LightSample>;
+}
+
+/// How the light of a local light source weakens with distance.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Falloff {
+    /// No falloff
+    Constant,
+    /// Inversely proportional to the distance
+    Linear,
+    /// Inversely proportional to the squared distance, as for real lights
+    #[default]
+    InverseSquare,
+}
+
+impl Falloff {
+    fn attenuation(&self, distance: f64) -> f64 {
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 / distance,
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+        }
+    }
+}
+
+/// Light shining equally in all directions from a single point.
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    position: Point3,
+    /// Irradiance at unit distance
+    intensity: Color,
+    falloff: Falloff,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            falloff: Falloff::default(),
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+
+        self
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.falloff.attenuation(distance) * self.intensity,
+        })
+    }
+}
+
+/// Point light restricted to a cone, fading out towards its edge.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    position: Point3,
+    /// Direction the cone points in
+    direction: Vec3,
+    /// Irradiance at unit distance on the cone axis
+    intensity: Color,
+    /// Angle in degrees between the axis and the edge of the cone
+    cone_angle: f64,
+    /// Fraction of the cone, from its edge inwards, over which the light fades out
+    edge_softness: f64,
+    falloff: Falloff,
+}
+
+impl SpotLight {
+    pub fn new(position: Point3, direction: Vec3, intensity: Color, cone_angle: f64) -> Self {
+        Self {
+            position,
+            direction: unit_vector(direction),
+            intensity,
+            cone_angle,
+            edge_softness: 0.15,
+            falloff: Falloff::default(),
+        }
+    }
+
+    pub fn with_edge_softness(mut self, edge_softness: f64) -> Self {
+        self.edge_softness = edge_softness.clamp(0.0, 1.0);
+
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+
+        self
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        let direction = to_light / distance;
+
+        let cos_total = f64::cos(self.cone_angle.to_radians());
+        let cos_inner = f64::cos((self.cone_angle * (1.0 - self.edge_softness)).to_radians());
+        let cos_theta = dot(-direction, self.direction);
+        if cos_theta <= cos_total {
+            return None;
+        }
+        let edge = if cos_theta >= cos_inner {
+            1.0
+        } else {
+            let x = (cos_theta - cos_total) / (cos_inner - cos_total);
+            x * x * (3.0 - 2.0 * x)
+        };
+
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: (edge * self.falloff.attenuation(distance)) * self.intensity,
+        })
+    }
+}
+
+/// Light from a distant source such as the sun, arriving from the same direction everywhere.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    direction: Vec3,
+    /// Irradiance on a surface facing the light
+    irradiance: Color,
+    /// Angular radius of the source in degrees, which softens shadows; 0 for a point source
+    angular_radius: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction: unit_vector(direction),
+            irradiance,
+            angular_radius: 0.0,
+        }
+    }
+
+    pub fn with_angular_radius(mut self, angular_radius: f64) -> Self {
+        self.angular_radius = angular_radius;
+
+        self
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: Point3) -> Option<LightSample> {
+        let direction = if self.angular_radius > 0.0 {
+            let cos_theta_max = f64::cos(self.angular_radius.to_radians());
+            Onb::new(-self.direction).transform(onb::random_in_cone(cos_theta_max))
+        } else {
+            -self.direction
+        };
+
+        Some(LightSample {
+            direction,
+            distance: INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{light::Light, prelude::*};
+
+#[derive(Debug, Default, Clone)]
+pub struct LightList {
+    pub lights: Vec<Rc<dyn Light>>,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    pub fn add(&mut self, light: Rc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+}
@@ -1,12 +1,14 @@
 use code::{
     camera::Camera,
+    checkpoint::Checkpointing,
     hittable_list::HittableList,
     material::{Dielectric, Lambertian, Material, Metal},
     prelude::*,
     sphere::Sphere,
+    stats,
 };
 
-fn main() -> std::io::Result<()> {
+fn random_spheres() -> HittableList {
     let mut world = HittableList::new();
 
     let ground_material = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
@@ -18,11 +20,11 @@ fn main() -> std::io::Result<()> {
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat: f64 = rand::random();
+            let choose_mat: f64 = rng::random();
             let center = Point3::new(
-                a as f64 + 0.9 * rand::random::<f64>(),
+                a as f64 + 0.9 * rng::random::<f64>(),
                 0.2,
-                b as f64 + 0.9 * rand::random::<f64>(),
+                b as f64 + 0.9 * rng::random::<f64>(),
             );
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
@@ -34,7 +36,7 @@ fn main() -> std::io::Result<()> {
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = rand::random_range(0.0..0.5);
+                    let fuzz = rng::random_range(0.0..0.5);
 
                     Rc::new(Metal::new(albedo, fuzz))
                 } else {
@@ -69,9 +71,21 @@ fn main() -> std::io::Result<()> {
         material3,
     )));
 
+    world
+}
+
+fn main() -> std::io::Result<()> {
     env_logger::init();
 
-    Camera::default()
+    // `--checkpoint <path>` periodically saves the render to `path` and resumes from it. The
+    // scene is then built from a fixed seed, so that it stays the same between runs.
+    let checkpoint = option_value("--checkpoint");
+    if checkpoint.is_some() {
+        rng::seed(42);
+    }
+    let world = stats::time_phase("scene build", random_spheres);
+
+    let mut camera = Camera::default()
         .with_aspect_ratio(16.0 / 9.0)
         .with_image_width(1200)
         .with_samples_per_pixel(500)
@@ -81,6 +95,22 @@ fn main() -> std::io::Result<()> {
         .with_lookat(Point3::new(0.0, 0.0, 0.0))
         .with_vup(Point3::new(0.0, 1.0, 0.0))
         .with_defocus_angle(0.6)
-        .with_focus_dist(10.0)
-        .render(&world)
+        .with_focus_dist(10.0);
+    if let Some(path) = checkpoint {
+        camera = camera.with_checkpoint(Checkpointing::new(path).with_resume(true));
+    }
+    // `--stats <path>` writes the render statistics to `path` as JSON.
+    if let Some(path) = option_value("--stats") {
+        camera = camera.with_stats_file(path);
+    }
+
+    camera.render(&world)
+}
+
+/// Returns the argument following `name` on the command line, if any.
+fn option_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    args.find(|arg| arg == name)?;
+
+    args.next()
 }
@@ -1,9 +1,67 @@
-use crate::{hittable::HitRecord, prelude::*};
+use crate::{
+    hittable::HitRecord,
+    medium::Scattering,
+    prelude::*,
+    spectrum::{self, ComplexIor, Dispersion},
+    texture::{SolidColor, Texture},
+    thin_film::ThinFilm,
+};
 
-pub trait Material {
+/// Surface scattering model. Colors a material returns are per wavelength when the incoming
+/// ray carries wavelengths (see [`spectrum::upsample`]).
+pub trait Material: std::fmt::Debug {
     fn scatter(&self, _r_in: Ray, _rec: HitRecord) -> Option<(Ray, Color)> {
         None
     }
+
+    /// Surface color used for the albedo output variable and as a denoising guide.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Fraction of the light arriving from unit `direction` that is scattered along the
+    /// reverse of `r_in`, including the cosine at the surface, for sampling lights directly.
+    /// Perfectly specular materials cannot be lit that way and return black.
+    fn eval(&self, _r_in: Ray, _rec: &HitRecord, _direction: Vec3) -> Color {
+        Color::default()
+    }
+
+    /// Probability density over solid angle of `scatter` choosing unit `direction`, or 0 for
+    /// perfectly specular materials.
+    fn pdf(&self, _r_in: Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Scatters like `scatter`, also returning the probability density over solid angle of
+    /// the scattered direction, or `None` if it was chosen by a specular event. Materials
+    /// combining specular and other scattering override this to tell them apart.
+    fn sample(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color, Option<f64>)> {
+        let (scattered, attenuation) = self.scatter(r_in, rec.clone())?;
+        let pdf = self.pdf(r_in, &rec, unit_vector(scattered.direction()));
+
+        Some((scattered, attenuation, (pdf > 0.0).then_some(pdf)))
+    }
+
+    /// Priority of the interior where the object overlaps other transparent objects, higher
+    /// winning, or `None` for materials light does not pass through.
+    fn priority(&self) -> Option<i32> {
+        None
+    }
+
+    /// Refractive index of the interior at `wavelength` nanometers.
+    fn ior(&self, _wavelength: f64) -> f64 {
+        1.0
+    }
+
+    /// Fraction of the light along `r` left after traveling `distance` through the interior.
+    fn transmittance(&self, _r: &Ray, _distance: f64) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Scattering by the interior, or `None` if light travels straight through it.
+    fn scattering(&self) -> Option<Scattering> {
+        None
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -18,7 +76,7 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
         let mut scatter_direction = rec.normal + random_unit_vector();
 
         // Catch degenerate scatter direction
@@ -26,47 +84,168 @@ impl Material for Lambertian {
             scatter_direction = rec.normal;
         }
 
-        let scattered = Ray::new(rec.p, scatter_direction);
-        let attenuation = self.albedo;
+        let scattered = Ray::new_with_time(rec.p, scatter_direction, r_in.time());
+        let attenuation = spectrum::upsample(self.albedo, r_in.wavelengths());
 
         Some((scattered, attenuation))
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cosine = f64::max(dot(rec.normal, direction), 0.0);
+
+        (cosine / PI) * spectrum::upsample(self.albedo, r_in.wavelengths())
+    }
+
+    fn pdf(&self, _r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        f64::max(dot(rec.normal, direction), 0.0) / PI
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct Metal {
     albedo: Color,
     fuzz: f64,
+    /// Interference coating over the metal, or `None` for a bare surface
+    thin_film: Option<ThinFilm>,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
         let fuzz = if fuzz < 1.0 { fuzz } else { 1.0 };
-        Self { albedo, fuzz }
+        Self {
+            albedo,
+            fuzz,
+            thin_film: None,
+        }
+    }
+
+    /// Coats the metal with `thin_film`, treating the albedo as the reflectance of the bare
+    /// metal at normal incidence.
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+
+        self
     }
 }
 
 impl Material for Metal {
+    #[allow(clippy::unnecessary_lazy_evaluations)]
     fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
         let mut reflected = reflect(r_in.direction(), rec.normal);
         reflected = unit_vector(reflected) + (self.fuzz * random_unit_vector());
-        let scattered = Ray::new(rec.p, reflected);
-        let attenuation = self.albedo;
+        let scattered = Ray::new_with_time(rec.p, reflected, r_in.time());
+        let albedo = spectrum::upsample(self.albedo, r_in.wavelengths());
+        let attenuation = match &self.thin_film {
+            Some(thin_film) => {
+                // The film needs the metal's complex index, fitted to the albedo following
+                // Gulbrandsen (2014) with the edge tint set to the albedo.
+                let fitted = albedo.e.map(|r| {
+                    let r = r.clamp(0.0, 0.99);
+                    let n_min = (1.0 - r) / (1.0 + r);
+                    let n_max = (1.0 + r.sqrt()) / (1.0 - r.sqrt());
+                    let n = r * n_min + (1.0 - r) * n_max;
+                    let k2 = ((n + 1.0) * (n + 1.0) * r - (n - 1.0) * (n - 1.0)) / (1.0 - r);
+                    (n, f64::sqrt(k2.max(0.0)))
+                });
+                let cosine = f64::min(dot(-unit_vector(r_in.direction()), rec.normal), 1.0);
+                thin_film.reflectances(
+                    &rec,
+                    cosine,
+                    spectrum::evaluation_wavelengths(r_in.wavelengths()),
+                    Vec3::new(rec.outer_ior, rec.outer_ior, rec.outer_ior),
+                    Vec3 {
+                        e: fitted.map(|(n, _)| n),
+                    },
+                    Vec3 {
+                        e: fitted.map(|(_, k)| k),
+                    },
+                )
+            }
+            None => albedo,
+        };
 
         (dot(scattered.direction(), rec.normal) > 0.0).then(|| (scattered, attenuation))
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct Dielectric {
-    /// Refractive index in vacuum or air, or the ratio of the material's refractive index over
-    /// the refractive index of the enclosing media
+    /// Refractive index in vacuum or air; the enclosing medium is accounted for when rendering
     refraction_index: f64,
+    /// Wavelength dependent refractive index replacing `refraction_index`, or `None` for a
+    /// material that refracts all wavelengths alike
+    dispersion: Option<Dispersion>,
+    /// Absorption coefficient per unit length traveled through the interior, per channel
+    absorption: Color,
+    /// Priority of the interior where it overlaps other transparent objects, higher winning
+    priority: i32,
+    /// Interference coating on the surface, or `None` for a bare surface
+    thin_film: Option<ThinFilm>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            dispersion: None,
+            absorption: Color::default(),
+            priority: 0,
+            thin_film: None,
+        }
+    }
+
+    /// Creates a material that splits white light into its colors, e.g.
+    /// `Dielectric::dispersive(Dispersion::flint())`.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            refraction_index: dispersion.ior(spectrum::D_LINE),
+            dispersion: Some(dispersion),
+            absorption: Color::default(),
+            priority: 0,
+            thin_film: None,
+        }
+    }
+
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+
+        self
+    }
+
+    /// Sets the absorption so that `transmittance` of the light is left after traveling
+    /// `distance` through the interior, e.g. a pale green for 1 unit of window glass.
+    pub fn with_transmittance(mut self, transmittance: Color, distance: f64) -> Self {
+        let absorption = |t: f64| -f64::ln(t.clamp(1e-6, 1.0)) / distance;
+        self.absorption = Color::new(
+            absorption(transmittance.x()),
+            absorption(transmittance.y()),
+            absorption(transmittance.z()),
+        );
+
+        self
+    }
+
+    /// Makes the object fill the space where it overlaps transparent objects of lower
+    /// priority, such as a liquid poured into a glass, whose walls then disappear inside it.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+
+        self
+    }
+
+    /// Coats the surface with `thin_film`, e.g. to render a soap bubble as a sphere of air.
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+
+        self
     }
 
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
@@ -77,12 +256,19 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, mut r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+        // Each wavelength refracts in its own direction, so a dispersive material follows one.
+        let (refraction_index, attenuation) = match self.dispersion {
+            Some(dispersion) => {
+                let (wavelength, weight) = r_in.single_wavelength();
+                (dispersion.ior(wavelength), weight)
+            }
+            None => (self.refraction_index, Color::new(1.0, 1.0, 1.0)),
+        };
         let ri = if rec.front_face {
-            1.0 / self.refraction_index
+            rec.outer_ior / refraction_index
         } else {
-            self.refraction_index
+            refraction_index / rec.outer_ior
         };
 
         let unit_direction = unit_vector(r_in.direction());
@@ -90,15 +276,469 @@ impl Material for Dielectric {
         let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction = if cannot_refract || Dielectric::reflectance(cos_theta, ri) > rand::random()
-        {
-            reflect(unit_direction, rec.normal)
-        } else {
-            refract(unit_direction, rec.normal, ri)
+        let (direction, attenuation) = match &self.thin_film {
+            Some(thin_film) if !cannot_refract => {
+                // The film reflects each wavelength differently, so reflection is chosen by the
+                // average reflectance and each wavelength weighted by its own.
+                let lambda = spectrum::evaluation_wavelengths(r_in.wavelengths());
+                let own = Vec3 {
+                    e: lambda.map(|lambda| self.ior(lambda)),
+                };
+                let outer = Vec3::new(rec.outer_ior, rec.outer_ior, rec.outer_ior);
+                let (incident, base) = if rec.front_face {
+                    (outer, own)
+                } else {
+                    (own, outer)
+                };
+                let reflectance = thin_film.reflectances(
+                    &rec,
+                    cos_theta,
+                    lambda,
+                    incident,
+                    base,
+                    Vec3::default(),
+                );
+
+                let probability = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+                if probability > rng::random() {
+                    let weight = reflectance / probability;
+                    (reflect(unit_direction, rec.normal), attenuation * weight)
+                } else {
+                    let weight = (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - probability);
+                    (
+                        refract(unit_direction, rec.normal, ri),
+                        attenuation * weight,
+                    )
+                }
+            }
+            _ if cannot_refract || Dielectric::reflectance(cos_theta, ri) > rng::random() => {
+                (reflect(unit_direction, rec.normal), attenuation)
+            }
+            _ => (refract(unit_direction, rec.normal, ri), attenuation),
         };
 
-        let scattered = Ray::new(rec.p, direction);
+        let scattered = Ray::new_with_time(rec.p, direction, r_in.time())
+            .with_wavelengths(r_in.wavelengths().copied())
+            .with_channel(r_in.channel());
 
         Some((scattered, attenuation))
     }
+
+    fn priority(&self) -> Option<i32> {
+        Some(self.priority)
+    }
+
+    fn ior(&self, wavelength: f64) -> f64 {
+        match self.dispersion {
+            Some(dispersion) => dispersion.ior(wavelength),
+            None => self.refraction_index,
+        }
+    }
+
+    /// Light is absorbed following the Beer-Lambert law.
+    fn transmittance(&self, r: &Ray, distance: f64) -> Color {
+        if self.absorption.near_zero() {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        let absorption = spectrum::upsample(self.absorption, r.wavelengths());
+        Color {
+            e: absorption.e.map(|a| f64::exp(-a * distance)),
+        }
+    }
+}
+
+/// Metal described by its measured complex index of refraction, whose color follows from the
+/// Fresnel equations at every wavelength.
+#[derive(Debug, Clone)]
+pub struct Conductor {
+    ior: ComplexIor,
+    fuzz: f64,
+    /// Interference coating over the metal, such as an oxide layer, or `None` for a bare surface
+    thin_film: Option<ThinFilm>,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, fuzz: f64) -> Self {
+        let fuzz = if fuzz < 1.0 { fuzz } else { 1.0 };
+        Self {
+            ior,
+            fuzz,
+            thin_film: None,
+        }
+    }
+
+    pub fn with_thin_film(mut self, thin_film: ThinFilm) -> Self {
+        self.thin_film = Some(thin_film);
+
+        self
+    }
+
+    /// Reflectance at `rec` at each of `lambda` for light arriving at `cosine` to the normal.
+    fn reflectance(&self, rec: &HitRecord, cosine: f64, lambda: [f64; 3]) -> Color {
+        let eta = self.ior.eta.values(lambda);
+        let k = self.ior.k.values(lambda);
+
+        if let Some(thin_film) = &self.thin_film {
+            let outer = Vec3::new(rec.outer_ior, rec.outer_ior, rec.outer_ior);
+            return thin_film.reflectances(rec, cosine, lambda, outer, eta, k);
+        }
+        Color::new(
+            fresnel_conductor(cosine, eta.x(), k.x()),
+            fresnel_conductor(cosine, eta.y(), k.y()),
+            fresnel_conductor(cosine, eta.z(), k.z()),
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+        let unit_direction = unit_vector(r_in.direction());
+        let reflected = reflect(unit_direction, rec.normal) + (self.fuzz * random_unit_vector());
+        let scattered = Ray::new_with_time(rec.p, reflected, r_in.time());
+
+        let cosine = f64::min(dot(-unit_direction, rec.normal), 1.0);
+        let lambda = spectrum::evaluation_wavelengths(r_in.wavelengths());
+        let attenuation = self.reflectance(&rec, cosine, lambda);
+
+        (dot(scattered.direction(), rec.normal) > 0.0).then_some((scattered, attenuation))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.reflectance(rec, 1.0, spectrum::RGB_WAVELENGTHS)
+    }
+}
+
+/// Smooth dielectric coating, such as a clear coat or lacquer, over another material. Light
+/// is reflected off the coating or passes through it to the base, in proportion to the
+/// Fresnel reflectance, and is tinted by the coating on the way in and out.
+#[derive(Debug, Clone)]
+pub struct Layered {
+    base: Rc<dyn Material>,
+    /// Refractive index of the coating
+    coat_ior: f64,
+    /// Perturbation of the reflection off the coating, as the fuzz of [`Metal`]
+    coat_roughness: f64,
+    /// Fraction of the light the coating lets through at normal incidence, per channel
+    coat_color: Color,
+}
+
+impl Layered {
+    pub fn new(base: Rc<dyn Material>, coat_ior: f64) -> Self {
+        Self {
+            base,
+            coat_ior,
+            coat_roughness: 0.0,
+            coat_color: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn with_coat_roughness(mut self, coat_roughness: f64) -> Self {
+        self.coat_roughness = coat_roughness.min(1.0);
+
+        self
+    }
+
+    pub fn with_coat_color(mut self, coat_color: Color) -> Self {
+        self.coat_color = coat_color;
+
+        self
+    }
+
+    /// Fresnel reflectance of the coating for light arriving at `cosine` to the normal.
+    fn coat_reflectance(&self, rec: &HitRecord, cosine: f64) -> f64 {
+        Dielectric::reflectance(cosine.abs().min(1.0), rec.outer_ior / self.coat_ior)
+    }
+
+    /// Fraction of the light left after passing through the coating into the base at
+    /// `cos_in` and back out at `cos_out` to the normal.
+    fn coat_transmittance(&self, r_in: &Ray, rec: &HitRecord, cos_in: f64, cos_out: f64) -> Color {
+        if self.coat_color.e == [1.0; 3] {
+            return self.coat_color;
+        }
+
+        // The path through the coating lengthens with the angle of the refracted light. Past
+        // total internal reflection, under a denser medium, no light crosses the coating.
+        let eta = rec.outer_ior / self.coat_ior;
+        let refracted_cos =
+            |cosine: f64| f64::sqrt(f64::max(0.0, 1.0 - eta * eta * (1.0 - cosine * cosine)));
+        let exponent = 0.5 / refracted_cos(cos_in) + 0.5 / refracted_cos(cos_out);
+        let color = spectrum::upsample(self.coat_color, r_in.wavelengths());
+        Color {
+            e: color.e.map(|c| c.max(0.0).powf(exponent)),
+        }
+    }
+}
+
+impl Material for Layered {
+    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+        self.sample(r_in, rec)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cos_in = f64::min(dot(-unit_vector(r_in.direction()), rec.normal), 1.0);
+        let cos_out = dot(direction, rec.normal).abs().min(1.0);
+        let transmitted = (1.0 - self.coat_reflectance(rec, cos_in))
+            * (1.0 - self.coat_reflectance(rec, cos_out));
+
+        transmitted
+            * self.coat_transmittance(&r_in, rec, cos_in, cos_out)
+            * self.base.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let cos_in = f64::min(dot(-unit_vector(r_in.direction()), rec.normal), 1.0);
+
+        (1.0 - self.coat_reflectance(rec, cos_in)) * self.base.pdf(r_in, rec, direction)
+    }
+
+    fn sample(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color, Option<f64>)> {
+        let unit_direction = unit_vector(r_in.direction());
+        let cos_in = f64::min(dot(-unit_direction, rec.normal), 1.0);
+        let reflectance = self.coat_reflectance(&rec, cos_in);
+
+        if reflectance > rng::random() {
+            let reflected =
+                reflect(unit_direction, rec.normal) + (self.coat_roughness * random_unit_vector());
+            let scattered = Ray::new_with_time(rec.p, reflected, r_in.time());
+            return (dot(scattered.direction(), rec.normal) > 0.0).then_some((
+                scattered,
+                Color::new(1.0, 1.0, 1.0),
+                None,
+            ));
+        }
+
+        let (scattered, attenuation, pdf) = self.base.sample(r_in, rec.clone())?;
+        let cos_out = dot(unit_vector(scattered.direction()), rec.normal)
+            .abs()
+            .min(1.0);
+        let transmitted = (1.0 - self.coat_reflectance(&rec, cos_out))
+            * self.coat_transmittance(&r_in, &rec, cos_in, cos_out);
+
+        Some((
+            scattered,
+            transmitted * attenuation,
+            pdf.map(|pdf| (1.0 - reflectance) * pdf),
+        ))
+    }
+}
+
+/// Blend of two materials, picking `b` over `a` with the probability given by a weight that
+/// may vary over the surface.
+#[derive(Debug, Clone)]
+pub struct Mix {
+    a: Rc<dyn Material>,
+    b: Rc<dyn Material>,
+    /// Texture whose luminance is the weight of `b`
+    weight: Rc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(a: Rc<dyn Material>, b: Rc<dyn Material>, weight: f64) -> Self {
+        let weight = Color::new(weight, weight, weight);
+        Self::textured(a, b, Rc::new(SolidColor::new(weight)))
+    }
+
+    pub fn textured(a: Rc<dyn Material>, b: Rc<dyn Material>, weight: Rc<dyn Texture>) -> Self {
+        Self { a, b, weight }
+    }
+
+    fn weight_at(&self, rec: &HitRecord) -> f64 {
+        luminance(self.weight.value(rec.u, rec.v, rec.p)).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+        self.sample(r_in, rec)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let weight = self.weight_at(rec);
+
+        (1.0 - weight) * self.a.albedo(rec) + weight * self.b.albedo(rec)
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let weight = self.weight_at(rec);
+
+        (1.0 - weight) * self.a.eval(r_in, rec, direction)
+            + weight * self.b.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let weight = self.weight_at(rec);
+
+        (1.0 - weight) * self.a.pdf(r_in, rec, direction)
+            + weight * self.b.pdf(r_in, rec, direction)
+    }
+
+    fn sample(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color, Option<f64>)> {
+        let material = if self.weight_at(&rec) > rng::random() {
+            &self.b
+        } else {
+            &self.a
+        };
+        let (scattered, attenuation, pdf) = material.sample(r_in, rec.clone())?;
+
+        // Directions either material could have chosen are weighted by the blended density.
+        let pdf = pdf.map(|_| self.pdf(r_in, &rec, unit_vector(scattered.direction())));
+        Some((scattered, attenuation, pdf))
+    }
+}
+
+/// Translucent material whose light scatters beneath the surface before leaving it again,
+/// traced as a random walk through the interior. Needs a closed object to walk inside. Light
+/// enters through a smooth surface and leaves diffusely, so that lights can be sampled where
+/// it leaves.
+///
+/// Lights are not sampled at the scattering events inside, since shadow rays cannot pass the
+/// smooth surface. Light reaches the interior only through walks that exit towards a light,
+/// or that hit the environment or sun by chance, so small or delta lights such as point, spot
+/// and directional lights take many samples to converge in thick, translucent media.
+#[derive(Debug, Clone)]
+pub struct Subsurface {
+    /// Smooth boundary between the interior and the outside
+    boundary: Dielectric,
+    scattering: Scattering,
+    /// Multiple scattering albedo, the color of the material deep inside
+    albedo: Color,
+}
+
+impl Subsurface {
+    pub fn new(scattering: Scattering, refraction_index: f64) -> Self {
+        Self {
+            boundary: Dielectric::new(refraction_index),
+            scattering,
+            albedo: scattering.multiple_scattering_albedo(),
+        }
+    }
+
+    /// Creates a material of the color `albedo` deep inside, in which light travels
+    /// `mean_free_path` between interactions on average, per channel. Longer paths make the
+    /// material look more translucent.
+    ///
+    /// Light is followed for at most [`MAX_SCATTERING_EVENTS`] scattering events inside and
+    /// counts as absorbed beyond that, which makes dense media of albedo close to 1 render
+    /// somewhat darker than `albedo`.
+    ///
+    /// [`MAX_SCATTERING_EVENTS`]: crate::medium::MAX_SCATTERING_EVENTS
+    pub fn from_albedo(albedo: Color, mean_free_path: Color, refraction_index: f64) -> Self {
+        Self {
+            albedo: Color {
+                e: albedo.e.map(|c| c.clamp(0.0, 1.0)),
+            },
+            ..Self::new(
+                Scattering::from_albedo(albedo, mean_free_path),
+                refraction_index,
+            )
+        }
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: f64) -> Self {
+        self.scattering.anisotropy = anisotropy.clamp(-0.99, 0.99);
+
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.boundary = self.boundary.with_priority(priority);
+
+        self
+    }
+
+    /// Fraction of the light reaching the surface from inside along `r_in` that leaves it.
+    fn exit_transmittance(&self, r_in: &Ray, rec: &HitRecord) -> f64 {
+        if rec.front_face {
+            return 0.0;
+        }
+
+        let ri = self.boundary.ior(r_in.wavelength()) / rec.outer_ior;
+        let cos_theta = f64::min(dot(-unit_vector(r_in.direction()), rec.normal), 1.0);
+        let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+        if ri * sin_theta > 1.0 {
+            return 0.0;
+        }
+
+        1.0 - Dielectric::reflectance(cos_theta, ri)
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: Ray, rec: HitRecord) -> Option<(Ray, Color)> {
+        if rec.front_face {
+            return self.boundary.scatter(r_in, rec);
+        }
+        if self.exit_transmittance(&r_in, &rec) <= rng::random() {
+            let reflected = reflect(unit_vector(r_in.direction()), rec.normal);
+            let scattered = Ray::new_with_time(rec.p, reflected, r_in.time());
+            return Some((scattered, Color::new(1.0, 1.0, 1.0)));
+        }
+
+        // The normal faces inwards, against the light arriving from inside.
+        let mut exit_direction = -rec.normal + random_unit_vector();
+        if exit_direction.near_zero() {
+            exit_direction = -rec.normal;
+        }
+        let scattered = Ray::new_with_time(rec.p, exit_direction, r_in.time());
+
+        Some((scattered, Color::new(1.0, 1.0, 1.0)))
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cosine = f64::max(dot(-rec.normal, direction), 0.0);
+        let f = self.exit_transmittance(&r_in, rec) * cosine / PI;
+
+        Color::new(f, f, f)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let cosine = f64::max(dot(-rec.normal, direction), 0.0);
+
+        self.exit_transmittance(&r_in, rec) * cosine / PI
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn priority(&self) -> Option<i32> {
+        self.boundary.priority()
+    }
+
+    fn ior(&self, wavelength: f64) -> f64 {
+        self.boundary.ior(wavelength)
+    }
+
+    fn scattering(&self) -> Option<Scattering> {
+        Some(self.scattering)
+    }
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index of refraction `eta` +
+/// i`k`, for light arriving at `cos_theta` to the normal.
+fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2b2 = f64::sqrt(t0 * t0 + 4.0 * eta2 * k2);
+    let t1 = a2b2 + cos2;
+    let a = f64::sqrt(f64::max(0.5 * (a2b2 + t0), 0.0));
+    let t2 = 2.0 * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
 }
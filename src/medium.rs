@@ -0,0 +1,207 @@
+use crate::{hittable::HitRecord, material::Material, onb::Onb, prelude::*, spectrum};
+
+/// Most scattering events followed inside a medium before a path is given up as absorbed.
+pub const MAX_SCATTERING_EVENTS: usize = 1024;
+
+/// Homogeneous interior that scatters and absorbs the light traveling through it, as in skin,
+/// wax or milk. Coefficients are per unit length and per channel.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Scattering {
+    pub sigma_a: Color,
+    pub sigma_s: Color,
+    /// Henyey-Greenstein asymmetry of the phase function, from -1 for back scattering through
+    /// 0 for isotropic to 1 for forward scattering
+    pub anisotropy: f64,
+}
+
+impl Scattering {
+    pub fn new(sigma_a: Color, sigma_s: Color) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            anisotropy: 0.0,
+        }
+    }
+
+    /// Coefficients giving a medium of multiple scattering `albedo`, the color of a thick
+    /// slab of it, in which light travels `mean_free_path` between interactions on average.
+    pub fn from_albedo(albedo: Color, mean_free_path: Color) -> Self {
+        let mut sigma_a = Color::default();
+        let mut sigma_s = Color::default();
+        for c in 0..3 {
+            // Single scattering albedo from the multiple scattering one, after Chiang et al.
+            // (2016).
+            let a = albedo[c].clamp(0.0, 1.0);
+            let s = 4.09712 + 4.20863 * a - f64::sqrt(9.59217 + 41.6808 * a + 17.7126 * a * a);
+            let single = 1.0 - s * s;
+            let sigma_t = 1.0 / mean_free_path[c].max(1e-6);
+            sigma_s[c] = single * sigma_t;
+            sigma_a[c] = sigma_t - sigma_s[c];
+        }
+
+        Self::new(sigma_a, sigma_s)
+    }
+
+    /// Multiple scattering albedo of the medium, the color of a thick slab of it, inverting
+    /// the fit used by [`Scattering::from_albedo`].
+    pub fn multiple_scattering_albedo(&self) -> Color {
+        let mut albedo = Color::default();
+        for c in 0..3 {
+            let sigma_t = self.sigma_a[c] + self.sigma_s[c];
+            let single = if sigma_t > 0.0 {
+                self.sigma_s[c] / sigma_t
+            } else {
+                0.0
+            };
+            // The fit is quadratic in the albedo, but its squared terms cancel, leaving a linear
+            // equation.
+            let d = 4.09712 - f64::sqrt((1.0 - single).clamp(0.0, 1.0));
+            let a = (d * d - 9.59217) / (41.6808 - 2.0 * 4.20863 * d);
+            albedo[c] = a.clamp(0.0, 1.0);
+        }
+
+        albedo
+    }
+
+    /// Samples how far `r` travels before scattering, given that the next surface is
+    /// `distance` away along it and that `throughput` of the light is carried so far. Returns
+    /// the distance to the scattering event, or `None` if the light reaches the surface, with
+    /// the factor to scale the throughput by.
+    pub fn sample_distance(
+        &self,
+        r: &Ray,
+        distance: f64,
+        throughput: Color,
+    ) -> (Option<f64>, Color) {
+        let sigma_a = spectrum::upsample(self.sigma_a, r.wavelengths());
+        let sigma_s = spectrum::upsample(self.sigma_s, r.wavelengths());
+        let sigma_t = sigma_a + sigma_s;
+        let transmittance = |t: f64| Color {
+            e: sigma_t.e.map(|sigma_t| f64::exp(-sigma_t * t)),
+        };
+
+        // Distances are sampled for a channel picked in proportion to the light it carries and
+        // weighted by the combined density of all three, which keeps the throughput bounded
+        // over long walks.
+        let total = throughput.x() + throughput.y() + throughput.z();
+        if total <= 0.0 {
+            return (None, Color::default());
+        }
+        let channel_probability = throughput / total;
+        let u = rng::random::<f64>();
+        let channel = if u < channel_probability.x() {
+            0
+        } else if u < channel_probability.x() + channel_probability.y() {
+            1
+        } else {
+            2
+        };
+        let t = -f64::ln(1.0 - rng::random::<f64>()) / sigma_t[channel];
+        if t < distance {
+            let tr = transmittance(t);
+            let pdf = dot(channel_probability, sigma_t * tr);
+            return (Some(t), (1.0 / pdf) * sigma_s * tr);
+        }
+
+        let tr = transmittance(distance);
+        let probability = dot(channel_probability, tr);
+        if probability == 0.0 {
+            return (None, Color::default());
+        }
+        (None, (1.0 / probability) * tr)
+    }
+
+    /// Samples the direction light traveling along unit `direction` scatters into.
+    pub fn sample_direction(&self, direction: Vec3) -> Vec3 {
+        let g = self.anisotropy;
+        let xi = rng::random::<f64>();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = f64::sqrt(f64::max(0.0, 1.0 - cos_theta * cos_theta));
+        let phi = 2.0 * PI * rng::random::<f64>();
+
+        Onb::new(direction).transform(Vec3::new(
+            sin_theta * f64::cos(phi),
+            sin_theta * f64::sin(phi),
+            cos_theta,
+        ))
+    }
+}
+
+/// Transparent objects a path is inside, in the order it entered them. Where objects overlap,
+/// the one of highest priority fills the space, and the surfaces of the others inside it are
+/// ignored.
+#[derive(Debug, Default, Clone)]
+pub struct MediumStack {
+    media: Vec<Rc<dyn Material>>,
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Medium the path travels through: the highest priority one it is inside, the most
+    /// recently entered on ties, or `None` outside all of them.
+    pub fn current(&self) -> Option<&Rc<dyn Material>> {
+        self.media.iter().max_by_key(|medium| priority(medium))
+    }
+
+    /// Whether the surface at `rec` separates two media, instead of lying inside an object of
+    /// higher priority.
+    pub fn is_interface(&self, rec: &HitRecord) -> bool {
+        let own = self.own_index(rec);
+        let priority_hit = priority(&rec.mat);
+
+        self.media
+            .iter()
+            .enumerate()
+            .all(|(index, medium)| Some(index) == own || priority(medium) <= priority_hit)
+    }
+
+    /// Medium on the other side of the surface at `rec` from the object it belongs to, or
+    /// `None` for vacuum or air.
+    pub fn outside(&self, rec: &HitRecord) -> Option<&Rc<dyn Material>> {
+        let own = self.own_index(rec);
+
+        self.media
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| Some(index) != own)
+            .max_by_key(|(_, medium)| priority(medium))
+            .map(|(_, medium)| medium)
+    }
+
+    /// Returns the media after passing through the surface at `rec`, into or out of its object.
+    pub fn crossed(&self, rec: &HitRecord) -> Self {
+        let mut media = self.clone();
+        match self.own_index(rec) {
+            Some(index) => {
+                media.media.remove(index);
+            }
+            None if rec.front_face => media.media.push(rec.mat.clone()),
+            None => {}
+        }
+
+        media
+    }
+
+    /// Position of the object whose surface is at `rec` if the path is leaving it.
+    fn own_index(&self, rec: &HitRecord) -> Option<usize> {
+        if rec.front_face {
+            return None;
+        }
+
+        self.media
+            .iter()
+            .rposition(|medium| Rc::ptr_eq(medium, &rec.mat))
+    }
+}
+
+fn priority(medium: &Rc<dyn Material>) -> i32 {
+    medium.priority().unwrap_or_default()
+}
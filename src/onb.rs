@@ -0,0 +1,52 @@
+use crate::prelude::*;
+
+/// Orthonormal basis with `w` along a given direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    /// Transforms `v` from basis coordinates to world coordinates.
+    pub fn transform(&self, v: Vec3) -> Vec3 {
+        v.x() * self.axis[0] + v.y() * self.axis[1] + v.z() * self.axis[2]
+    }
+}
+
+/// Returns a random direction within `cos_theta_max` of the +z axis, uniform over solid angle.
+pub fn random_in_cone(cos_theta_max: f64) -> Vec3 {
+    let cos_theta = 1.0 - rng::random::<f64>() * (1.0 - cos_theta_max);
+    let sin_theta = f64::sqrt(f64::max(0.0, 1.0 - cos_theta * cos_theta));
+    let phi = 2.0 * PI * rng::random::<f64>();
+
+    Vec3::new(
+        f64::cos(phi) * sin_theta,
+        f64::sin(phi) * sin_theta,
+        cos_theta,
+    )
+}
@@ -0,0 +1,98 @@
+/// Camera specified like a real one. Field of view, depth of field, motion blur and exposure
+/// all follow from these settings instead of being chosen directly.
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalCamera {
+    /// Lens focal length in millimeters
+    pub focal_length: f64,
+    /// Sensor width in millimeters
+    pub sensor_width: f64,
+    /// Sensor height in millimeters
+    pub sensor_height: f64,
+    /// Aperture f-number, the focal length over the aperture diameter
+    pub f_stop: f64,
+    /// Time the shutter stays open, in seconds
+    pub shutter_speed: f64,
+    /// Sensor sensitivity
+    pub iso: f64,
+    /// Length of one scene unit in meters
+    pub meters_per_unit: f64,
+}
+
+impl Default for PhysicalCamera {
+    /// A 50 mm lens on a full-frame sensor at the "sunny 16" settings.
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            f_stop: 16.0,
+            shutter_speed: 1.0 / 100.0,
+            iso: 100.0,
+            meters_per_unit: 1.0,
+        }
+    }
+}
+
+impl PhysicalCamera {
+    pub fn with_focal_length(mut self, focal_length: f64) -> Self {
+        self.focal_length = focal_length;
+
+        self
+    }
+
+    pub fn with_sensor_size(mut self, sensor_width: f64, sensor_height: f64) -> Self {
+        self.sensor_width = sensor_width;
+        self.sensor_height = sensor_height;
+
+        self
+    }
+
+    pub fn with_f_stop(mut self, f_stop: f64) -> Self {
+        self.f_stop = f_stop;
+
+        self
+    }
+
+    pub fn with_shutter_speed(mut self, shutter_speed: f64) -> Self {
+        self.shutter_speed = shutter_speed;
+
+        self
+    }
+
+    pub fn with_iso(mut self, iso: f64) -> Self {
+        self.iso = iso;
+
+        self
+    }
+
+    pub fn with_meters_per_unit(mut self, meters_per_unit: f64) -> Self {
+        self.meters_per_unit = meters_per_unit;
+
+        self
+    }
+
+    /// Vertical field of view in degrees for an image of the given aspect ratio, cropped from
+    /// the largest area of the sensor that has that aspect ratio.
+    pub fn vfov(&self, aspect_ratio: f64) -> f64 {
+        let half_height = f64::min(self.sensor_height, self.sensor_width / aspect_ratio) / 2.0;
+
+        2.0 * f64::atan(half_height / self.focal_length).to_degrees()
+    }
+
+    /// Defocus angle in degrees when focused at `focus_dist` scene units.
+    pub fn defocus_angle(&self, focus_dist: f64) -> f64 {
+        let aperture_radius_mm = self.focal_length / self.f_stop / 2.0;
+        let aperture_radius = aperture_radius_mm / 1000.0 / self.meters_per_unit;
+
+        2.0 * f64::atan(aperture_radius / focus_dist).to_degrees()
+    }
+
+    /// Exposure adjustment in stops, relative to the "sunny 16" settings (f/16, 1/100 s at
+    /// ISO 100), which leave the scene brightness unchanged.
+    pub fn exposure(&self) -> f64 {
+        let exposure = self.shutter_speed * self.iso / (self.f_stop * self.f_stop);
+        let reference = (1.0 / 100.0) * 100.0 / (16.0 * 16.0);
+
+        f64::log2(exposure / reference)
+    }
+}
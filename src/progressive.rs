@@ -0,0 +1,84 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Shared flag used to stop a progressive render from another thread or from its callback.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Stopping conditions for a progressive render. The render stops at whichever comes first.
+#[derive(Debug, Clone)]
+pub struct Progressive {
+    /// Samples added to every pixel in each pass
+    pub samples_per_pass: i32,
+    /// Wall-clock time after which the render stops, within the current pass as soon as the
+    /// row being sampled is done
+    pub time_limit: Option<Duration>,
+    /// Samples per pixel after which the render is complete
+    pub sample_limit: Option<i32>,
+    /// Token that stops the render, as soon as the row being sampled is done, when cancelled
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Self {
+            samples_per_pass: 1,
+            time_limit: None,
+            sample_limit: None,
+            cancel: None,
+        }
+    }
+}
+
+impl Progressive {
+    pub fn with_samples_per_pass(mut self, samples_per_pass: i32) -> Self {
+        self.samples_per_pass = samples_per_pass;
+
+        self
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+
+        self
+    }
+
+    pub fn with_sample_limit(mut self, sample_limit: i32) -> Self {
+        self.sample_limit = Some(sample_limit);
+
+        self
+    }
+
+    pub fn with_cancellation_token(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::prelude::*;
+
+/// How a camera maps image positions to rays.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Projection {
+    /// Thin-lens perspective projection with vertical field of view `vfov`
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction, covering `view_width` world units horizontally
+    Orthographic { view_width: f64 },
+    /// Fisheye lens whose image circle spans the image width and `fov` degrees
+    Fisheye { mapping: FisheyeMapping, fov: f64 },
+    /// Full 360° by 180° latitude-longitude panorama; use a 2:1 aspect ratio
+    Equirectangular,
+    /// Six 90° faces in a 3 by 2 grid, +X, -X, +Y on top and -Y, +Z, -Z below, in the camera
+    /// frame (X right, Y up, Z behind the camera). Each face is upright as seen from the camera
+    /// position, the up and down faces with the forward direction towards the horizon. Use a
+    /// 3:2 aspect ratio
+    CubeMap,
+}
+
+/// Relation between the angle from the optical axis and the distance from the image center.
+#[derive(Debug, Clone, Copy)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle
+    Equidistant,
+    /// Equal areas on the image cover equal solid angles
+    Equisolid,
+}
+
+impl Projection {
+    /// Returns the origin offset and direction, in the camera frame (x right, y up, z opposite
+    /// the view direction), of the ray through image position `s`, `t`, where both range over
+    /// `[0, 1]` from the upper left corner. Returns `None` for positions the projection does
+    /// not cover. Not used for [`Projection::Perspective`], which the camera handles itself.
+    pub fn camera_ray(&self, s: f64, t: f64, aspect_ratio: f64) -> Option<(Vec3, Vec3)> {
+        match *self {
+            Self::Perspective => None,
+            Self::Orthographic { view_width } => {
+                let view_height = view_width / aspect_ratio;
+                let origin = Vec3::new((s - 0.5) * view_width, (0.5 - t) * view_height, 0.0);
+                Some((origin, Vec3::new(0.0, 0.0, -1.0)))
+            }
+            Self::Fisheye { mapping, fov } => {
+                // Image coordinates relative to the image circle, y up.
+                let x = 2.0 * s - 1.0;
+                let y = (1.0 - 2.0 * t) / aspect_ratio;
+                let r = f64::sqrt(x * x + y * y);
+                let half_fov = (fov / 2.0).to_radians();
+
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => {
+                        let sin_half_theta = r * f64::sin(half_fov / 2.0);
+                        if sin_half_theta > 1.0 {
+                            return None;
+                        }
+                        2.0 * f64::asin(sin_half_theta)
+                    }
+                };
+                if r > 1.0 || theta > PI {
+                    return None;
+                }
+
+                let phi = f64::atan2(y, x);
+                let direction = Vec3::new(
+                    f64::sin(theta) * f64::cos(phi),
+                    f64::sin(theta) * f64::sin(phi),
+                    -f64::cos(theta),
+                );
+                Some((Vec3::default(), direction))
+            }
+            Self::Equirectangular => {
+                let phi = (s - 0.5) * 2.0 * PI;
+                let theta = (0.5 - t) * PI;
+                let direction = Vec3::new(
+                    f64::cos(theta) * f64::sin(phi),
+                    f64::sin(theta),
+                    -f64::cos(theta) * f64::cos(phi),
+                );
+                Some((Vec3::default(), direction))
+            }
+            Self::CubeMap => {
+                let column = f64::min(f64::floor(3.0 * s), 2.0);
+                let row = f64::min(f64::floor(2.0 * t), 1.0);
+                // Face coordinates in [-1, 1], a to the right and b downwards.
+                let a = 2.0 * (3.0 * s - column) - 1.0;
+                let b = 2.0 * (2.0 * t - row) - 1.0;
+
+                let direction = match (row as i32, column as i32) {
+                    (0, 0) => Vec3::new(1.0, -b, a),
+                    (0, 1) => Vec3::new(-1.0, -b, -a),
+                    (0, _) => Vec3::new(a, 1.0, -b),
+                    (_, 0) => Vec3::new(a, -1.0, b),
+                    (_, 1) => Vec3::new(-a, -b, 1.0),
+                    (_, _) => Vec3::new(a, -b, -1.0),
+                };
+                Some((Vec3::default(), direction))
+            }
+        }
+    }
+}
@@ -1,14 +1,46 @@
-use crate::vec3::{Point3, Vec3};
+use crate::{
+    color::Color,
+    spectrum::{self, Wavelengths},
+    vec3::{Point3, Vec3},
+};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    tm: f64,
+    /// Wavelengths of a spectral path, or `None` when rendering in RGB
+    wavelengths: Option<Wavelengths>,
+    /// Color channel that alone carries the light of an RGB path after a wavelength dependent
+    /// direction change
+    channel: Option<usize>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self::new_with_time(origin, direction, 0.0)
+    }
+
+    pub fn new_with_time(origin: Point3, direction: Vec3, time: f64) -> Self {
+        Self {
+            origin,
+            direction,
+            tm: time,
+            wavelengths: None,
+            channel: None,
+        }
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Option<Wavelengths>) -> Self {
+        self.wavelengths = wavelengths;
+
+        self
+    }
+
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+
+        self
     }
 
     pub fn origin(&self) -> Point3 {
@@ -19,6 +51,33 @@ impl Ray {
         self.direction
     }
 
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
+    pub fn wavelengths(&self) -> Option<&Wavelengths> {
+        self.wavelengths.as_ref()
+    }
+
+    pub fn channel(&self) -> Option<usize> {
+        self.channel
+    }
+
+    /// Wavelength in nanometers that refraction along the ray is evaluated at: the hero
+    /// wavelength of a spectral path, the color channel an RGB path carries, or the d line.
+    pub fn wavelength(&self) -> f64 {
+        match (self.wavelengths, self.channel) {
+            (Some(wavelengths), _) => wavelengths.hero(),
+            (None, Some(channel)) => spectrum::RGB_WAVELENGTHS[channel],
+            (None, None) => spectrum::D_LINE,
+        }
+    }
+
+    /// Restricts the path to one wavelength, see [`spectrum::single_wavelength`].
+    pub fn single_wavelength(&mut self) -> (f64, Color) {
+        spectrum::single_wavelength(self.wavelengths.as_mut(), &mut self.channel)
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + t * self.direction
     }
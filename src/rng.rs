@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+
+use rand::{
+    Rng, SeedableRng,
+    distr::{Distribution, StandardUniform, uniform::SampleRange, uniform::SampleUniform},
+};
+use rand_chacha::ChaCha8Rng;
+
+thread_local! {
+    static RNG: RefCell<ChaCha8Rng> = RefCell::new(ChaCha8Rng::seed_from_u64(rand::random()));
+}
+
+/// Snapshot of the renderer's random number generator, enough to continue its exact sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    pub word_pos: u128,
+}
+
+/// Reseeds the random number generator of the current thread, making everything drawn from it
+/// afterwards reproducible.
+pub fn seed(seed: u64) {
+    RNG.with_borrow_mut(|rng| *rng = ChaCha8Rng::seed_from_u64(seed));
+}
+
+pub fn state() -> RngState {
+    RNG.with_borrow(|rng| RngState {
+        seed: rng.get_seed(),
+        stream: rng.get_stream(),
+        word_pos: rng.get_word_pos(),
+    })
+}
+
+pub fn set_state(state: RngState) {
+    RNG.with_borrow_mut(|rng| {
+        *rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+    });
+}
+
+/// Returns a random value, like [`rand::random`].
+pub fn random<T>() -> T
+where
+    StandardUniform: Distribution<T>,
+{
+    RNG.with_borrow_mut(|rng| rng.random())
+}
+
+/// Returns a random value in `range`, like [`rand::random_range`].
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    RNG.with_borrow_mut(|rng| rng.random_range(range))
+}
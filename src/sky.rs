@@ -0,0 +1,235 @@
+use crate::{light::DirectionalLight, onb::Onb, prelude::*};
+
+/// Angular radius of the sun in degrees.
+const SUN_ANGULAR_RADIUS: f64 = 0.2667;
+/// Illuminance of the sun above the atmosphere, in thousands of lux.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+/// Wavelengths in micrometers that the sun's extinction is evaluated at for each channel.
+const CHANNEL_WAVELENGTHS: [f64; 3] = [0.65, 0.57, 0.475];
+
+/// Preetham, Shirley and Smits' analytic model of the clear daylight sky, with a matching sun.
+/// Radiance below the horizon is that of a diffuse ground lit by the sky and sun.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    /// Unit direction towards the sun
+    sun_direction: Vec3,
+    /// Haziness of the atmosphere, from 2 for very clear to 10 for hazy
+    turbidity: f64,
+    ground_albedo: Color,
+    /// Scale from luminance in thousands of candela per square meter to scene radiance
+    intensity: f64,
+    /// Whether the sun is sampled as a light and visible as a disk
+    sun: bool,
+
+    /// Luminance and chromaticity in the zenith
+    zenith: [f64; 3],
+    /// Perez distribution coefficients of luminance and of both chromaticity coordinates
+    perez: [[f64; 5]; 3],
+    /// Irradiance of the sun on a surface facing it
+    sun_irradiance: Color,
+    ground_radiance: Color,
+}
+
+impl Sky {
+    pub fn new(sun_direction: Vec3) -> Self {
+        let mut sky = Self {
+            sun_direction: unit_vector(sun_direction),
+            turbidity: 3.0,
+            ground_albedo: Color::new(0.3, 0.3, 0.3),
+            intensity: 0.1,
+            sun: true,
+            zenith: [0.0; 3],
+            perez: [[0.0; 5]; 3],
+            sun_irradiance: Color::default(),
+            ground_radiance: Color::default(),
+        };
+        sky.update();
+
+        sky
+    }
+
+    /// Sky with the sun at `elevation` degrees above the horizon and `azimuth` degrees from -z
+    /// towards +x, the same way round as an equirectangular projection.
+    pub fn from_sun_angles(elevation: f64, azimuth: f64) -> Self {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        Self::new(Vec3::new(
+            f64::cos(elevation) * f64::sin(azimuth),
+            f64::sin(elevation),
+            -f64::cos(elevation) * f64::cos(azimuth),
+        ))
+    }
+
+    pub fn with_turbidity(mut self, turbidity: f64) -> Self {
+        self.turbidity = turbidity.clamp(1.7, 10.0);
+        self.update();
+
+        self
+    }
+
+    pub fn with_ground_albedo(mut self, ground_albedo: Color) -> Self {
+        self.ground_albedo = ground_albedo;
+        self.update();
+
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self.update();
+
+        self
+    }
+
+    pub fn with_sun(mut self, sun: bool) -> Self {
+        self.sun = sun;
+        self.update();
+
+        self
+    }
+
+    /// Radiance of the sky, or of the ground below the horizon, from `direction`, not counting
+    /// the sun disk.
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let direction = unit_vector(direction);
+        if direction.y() < 0.0 {
+            return self.ground_radiance;
+        }
+
+        self.sky_radiance(direction)
+    }
+
+    /// Radiance of the sun disk from `direction`, black outside the disk.
+    pub fn sun_disk_radiance(&self, direction: Vec3) -> Color {
+        let cos_radius = f64::cos(SUN_ANGULAR_RADIUS.to_radians());
+        if !self.sun || dot(unit_vector(direction), self.sun_direction) < cos_radius {
+            return Color::default();
+        }
+
+        self.sun_irradiance / (2.0 * PI * (1.0 - cos_radius))
+    }
+
+    /// The sun as a light source, or `None` if it is disabled or below the horizon.
+    pub fn sun_light(&self) -> Option<DirectionalLight> {
+        (self.sun && self.sun_direction.y() > 0.0).then(|| {
+            DirectionalLight::new(-self.sun_direction, self.sun_irradiance)
+                .with_angular_radius(SUN_ANGULAR_RADIUS)
+        })
+    }
+
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        // The model does not hold below the horizon, so stay just above it.
+        let theta = f64::acos(direction.y().clamp(0.01, 1.0));
+        let gamma = f64::acos(dot(direction, self.sun_direction).clamp(-1.0, 1.0));
+        let theta_sun = self.sun_zenith_angle();
+
+        let [luminance, x, y] = [0, 1, 2].map(|k| {
+            self.zenith[k] * perez(&self.perez[k], theta, gamma)
+                / perez(&self.perez[k], 0.0, theta_sun)
+        });
+        let xyz = Vec3::new(x * luminance / y, luminance, (1.0 - x - y) * luminance / y);
+
+        self.intensity * xyz_to_rgb(xyz)
+    }
+
+    fn sun_zenith_angle(&self) -> f64 {
+        f64::acos(self.sun_direction.y().clamp(0.01, 1.0))
+    }
+
+    /// Recomputes the model coefficients after a setting changed.
+    fn update(&mut self) {
+        let t = self.turbidity;
+        let theta_sun = self.sun_zenith_angle();
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * f64::tan(chi) - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let angles = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let row = |r: [f64; 4]| (0..4).map(|k| r[k] * angles[k]).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        self.zenith = [zenith_luminance, zenith_x, zenith_y];
+        self.perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // Sunlight is attenuated by Rayleigh and aerosol scattering along its path through the
+        // air mass, using the extinction formulas of the same paper.
+        let theta_degrees = theta_sun.to_degrees();
+        let air_mass =
+            1.0 / (f64::cos(theta_sun) + 0.15 * f64::powf(93.885 - theta_degrees, -1.253));
+        let beta = 0.04608 * t - 0.04586;
+        let transmittance = CHANNEL_WAVELENGTHS.map(|lambda| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            f64::exp(-(rayleigh + aerosol) * air_mass)
+        });
+        self.sun_irradiance = if self.sun {
+            (self.intensity * SOLAR_ILLUMINANCE)
+                * Color::new(transmittance[0], transmittance[1], transmittance[2])
+        } else {
+            Color::default()
+        };
+
+        // Light the ground with the sky integrated over the upper hemisphere and the sun.
+        const STEPS: usize = 32;
+        let frame = Onb::new(Vec3::new(0.0, 1.0, 0.0));
+        let mut sky_irradiance = Color::default();
+        for i in 0..STEPS {
+            let theta = (i as f64 + 0.5) / STEPS as f64 * PI / 2.0;
+            for j in 0..4 * STEPS {
+                let phi = (j as f64 + 0.5) / (4 * STEPS) as f64 * 2.0 * PI;
+                let local = Vec3::new(
+                    f64::sin(theta) * f64::cos(phi),
+                    f64::sin(theta) * f64::sin(phi),
+                    f64::cos(theta),
+                );
+                let solid_angle =
+                    f64::sin(theta) * (PI / 2.0 / STEPS as f64) * (2.0 * PI / (4 * STEPS) as f64);
+                sky_irradiance +=
+                    (f64::cos(theta) * solid_angle) * self.sky_radiance(frame.transform(local));
+            }
+        }
+        let sun_on_ground = f64::max(self.sun_direction.y(), 0.0) * self.sun_irradiance;
+        self.ground_radiance = (self.ground_albedo / PI) * (sky_irradiance + sun_on_ground);
+    }
+}
+
+/// Perez et al.'s sky distribution function for zenith angle `theta` and angle `gamma` from
+/// the sun.
+fn perez(coefficients: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = f64::cos(gamma);
+
+    (1.0 + a * f64::exp(b / f64::cos(theta)))
+        * (1.0 + c * f64::exp(d * gamma) + e * cos_gamma * cos_gamma)
+}
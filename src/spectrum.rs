@@ -0,0 +1,362 @@
+use std::sync::OnceLock;
+
+use crate::prelude::*;
+
+/// Shortest wavelength sampled, in nanometers.
+pub const LAMBDA_MIN: f64 = 380.0;
+/// Longest wavelength sampled, in nanometers.
+pub const LAMBDA_MAX: f64 = 720.0;
+/// Wavelengths in nanometers that spectral data is evaluated at for the red, green and blue
+/// channels when rendering in RGB.
+pub const RGB_WAVELENGTHS: [f64; 3] = [610.0, 550.0, 465.0];
+
+/// Wavelength of the helium d line in nanometers, which refractive indices are quoted at.
+pub const D_LINE: f64 = 587.6;
+
+/// Wavelengths carried by a path in spectral mode: a uniformly sampled hero wavelength and two
+/// more spaced evenly across the visible range. Colors along such a path hold one value per
+/// wavelength instead of red, green and blue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wavelengths {
+    /// Wavelengths in nanometers, the hero first
+    pub lambda: [f64; 3],
+    /// Whether only the hero wavelength still carries light, after a wavelength dependent
+    /// direction change
+    pub terminated: bool,
+}
+
+impl Wavelengths {
+    pub fn sample() -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + range * rng::random::<f64>();
+        let lambda = [0.0, 1.0, 2.0].map(|k| {
+            let lambda = hero + k * range / 3.0;
+            if lambda >= LAMBDA_MAX {
+                lambda - range
+            } else {
+                lambda
+            }
+        });
+
+        Self {
+            lambda,
+            terminated: false,
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Keeps only the hero wavelength, for a scattering event whose direction depends on the
+    /// wavelength. Returns the factor to scale the per-wavelength weights of the event by,
+    /// which drops the secondary wavelengths and compensates the hero for them.
+    pub fn terminate_secondary(&mut self) -> Color {
+        if self.terminated {
+            return Color::new(1.0, 0.0, 0.0);
+        }
+        self.terminated = true;
+
+        Color::new(3.0, 0.0, 0.0)
+    }
+
+    /// Converts one value per wavelength into linear RGB, white balanced so that a flat
+    /// spectrum of 1 becomes white.
+    pub fn to_rgb(&self, values: Color) -> Color {
+        let mut xyz = Vec3::default();
+        for (lambda, value) in self.lambda.iter().zip(values.e) {
+            xyz += value * cie_xyz(*lambda);
+        }
+        let rgb = xyz_to_rgb((LAMBDA_MAX - LAMBDA_MIN) / 3.0 * xyz);
+
+        let white = white_rgb();
+        Color::new(
+            rgb.x() / white.x(),
+            rgb.y() / white.y(),
+            rgb.z() / white.z(),
+        )
+    }
+}
+
+/// Returns `rgb` as one value per wavelength of `wavelengths`, upsampled into a smooth
+/// spectrum, or unchanged when rendering in RGB.
+pub fn upsample(rgb: Color, wavelengths: Option<&Wavelengths>) -> Color {
+    match wavelengths {
+        Some(wavelengths) => Color {
+            e: wavelengths.lambda.map(|lambda| smits(rgb, lambda)),
+        },
+        None => rgb,
+    }
+}
+
+/// Restricts a path to one wavelength for a wavelength dependent direction change. Returns the
+/// wavelength in nanometers and the factor to scale the event's weight by: a spectral path
+/// keeps only its hero wavelength, and an RGB path picks a random color channel `channel`
+/// that alone carries its light from then on.
+pub fn single_wavelength(
+    wavelengths: Option<&mut Wavelengths>,
+    channel: &mut Option<usize>,
+) -> (f64, Color) {
+    if let Some(wavelengths) = wavelengths {
+        return (wavelengths.hero(), wavelengths.terminate_secondary());
+    }
+    if let Some(channel) = *channel {
+        return (RGB_WAVELENGTHS[channel], Color::new(1.0, 1.0, 1.0));
+    }
+
+    let selected = rng::random_range(0..3);
+    *channel = Some(selected);
+    let mut weight = Color::default();
+    weight.e[selected] = 3.0;
+    (RGB_WAVELENGTHS[selected], weight)
+}
+
+/// Wavelengths to evaluate spectral data at: those of a spectral path, or one per channel.
+pub fn evaluation_wavelengths(wavelengths: Option<&Wavelengths>) -> [f64; 3] {
+    wavelengths.map_or(RGB_WAVELENGTHS, |wavelengths| wavelengths.lambda)
+}
+
+/// Spectral quantity measured at a set of wavelengths and linearly interpolated between them.
+#[derive(Debug, Clone)]
+pub struct TabulatedSpectrum {
+    /// Pairs of wavelength in nanometers and value, in increasing wavelength
+    samples: Vec<(f64, f64)>,
+}
+
+impl TabulatedSpectrum {
+    pub fn new(mut samples: Vec<(f64, f64)>) -> Self {
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { samples }
+    }
+
+    /// Value at `lambda` nanometers, held constant beyond the measured range.
+    pub fn value(&self, lambda: f64) -> f64 {
+        let next = self.samples.partition_point(|&(l, _)| l < lambda);
+        match (
+            next.checked_sub(1).map(|i| self.samples[i]),
+            self.samples.get(next),
+        ) {
+            (Some((l0, v0)), Some(&(l1, v1))) => v0 + (v1 - v0) * (lambda - l0) / (l1 - l0),
+            (Some((_, v)), None) | (None, Some(&(_, v))) => v,
+            (None, None) => 0.0,
+        }
+    }
+
+    /// Values at each of `lambda`.
+    pub fn values(&self, lambda: [f64; 3]) -> Vec3 {
+        Vec3 {
+            e: lambda.map(|lambda| self.value(lambda)),
+        }
+    }
+}
+
+/// Refractive index of a transparent material as a function of wavelength.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    /// Cauchy's equation `n = a + b / λ²`, with `λ` in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// Three term Sellmeier equation `n² = 1 + Σ bᵢ λ² / (λ² - cᵢ)`, with `λ` in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// Cauchy's equation fitted to the index `nd` at the d line and to the dispersion between
+    /// the F and C lines given by the Abbe number `vd`.
+    pub fn from_abbe(nd: f64, vd: f64) -> Self {
+        let b = (nd - 1.0) / vd / (1.0 / (0.4861 * 0.4861) - 1.0 / (0.6563 * 0.6563));
+        let a = nd - b / (0.5876 * 0.5876);
+        Self::Cauchy { a, b }
+    }
+
+    /// Schott N-BK7 borosilicate crown glass.
+    pub fn bk7() -> Self {
+        Self::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    /// Typical soda-lime crown glass.
+    pub fn crown() -> Self {
+        Self::from_abbe(1.523, 58.6)
+    }
+
+    /// Schott SF11 dense flint glass.
+    pub fn flint() -> Self {
+        Self::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        }
+    }
+
+    /// Diamond, after Peter (1923).
+    pub fn diamond() -> Self {
+        Self::Sellmeier {
+            b: [4.3356, 0.3306, 0.0],
+            c: [0.1060 * 0.1060, 0.1750 * 0.1750, 0.0],
+        }
+    }
+
+    /// Refractive index at `lambda` nanometers.
+    pub fn ior(&self, lambda: f64) -> f64 {
+        let l2 = (lambda / 1000.0) * (lambda / 1000.0);
+        match *self {
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => {
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                f64::sqrt(n2)
+            }
+        }
+    }
+}
+
+/// Complex index of refraction of a conductor, measured over the visible range.
+#[derive(Debug, Clone)]
+pub struct ComplexIor {
+    pub eta: TabulatedSpectrum,
+    pub k: TabulatedSpectrum,
+}
+
+impl ComplexIor {
+    /// Creates the data from rows of wavelength in nanometers, `eta` and `k`.
+    pub fn new(data: &[(f64, f64, f64)]) -> Self {
+        Self {
+            eta: TabulatedSpectrum::new(data.iter().map(|&(l, eta, _)| (l, eta)).collect()),
+            k: TabulatedSpectrum::new(data.iter().map(|&(l, _, k)| (l, k)).collect()),
+        }
+    }
+
+    /// Gold, after Johnson and Christy (1972).
+    pub fn gold() -> Self {
+        Self::new(&[
+            (400.0, 1.658, 1.956),
+            (450.0, 1.400, 1.880),
+            (500.0, 0.970, 1.870),
+            (550.0, 0.430, 2.455),
+            (600.0, 0.250, 2.980),
+            (650.0, 0.166, 3.150),
+            (700.0, 0.160, 3.950),
+        ])
+    }
+
+    /// Silver, after Johnson and Christy (1972).
+    pub fn silver() -> Self {
+        Self::new(&[
+            (400.0, 0.050, 2.100),
+            (450.0, 0.040, 2.650),
+            (500.0, 0.050, 3.090),
+            (550.0, 0.059, 3.590),
+            (600.0, 0.060, 4.000),
+            (650.0, 0.050, 4.480),
+            (700.0, 0.040, 4.850),
+        ])
+    }
+
+    /// Copper, after Johnson and Christy (1972).
+    pub fn copper() -> Self {
+        Self::new(&[
+            (400.0, 1.180, 2.210),
+            (450.0, 1.170, 2.400),
+            (500.0, 1.120, 2.600),
+            (550.0, 1.020, 2.580),
+            (600.0, 0.290, 3.240),
+            (650.0, 0.210, 3.670),
+            (700.0, 0.210, 4.200),
+        ])
+    }
+
+    /// Aluminium, after Rakić (1995).
+    pub fn aluminium() -> Self {
+        Self::new(&[
+            (400.0, 0.490, 4.860),
+            (450.0, 0.620, 5.470),
+            (500.0, 0.770, 6.080),
+            (550.0, 0.960, 6.690),
+            (600.0, 1.200, 7.260),
+            (650.0, 1.470, 7.790),
+            (700.0, 1.830, 8.310),
+        ])
+    }
+}
+
+/// CIE 1931 color matching functions at `lambda` nanometers, using the multi-lobe Gaussian fit
+/// of Wyman, Sloan and Shirley (2013).
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let g = |mu: f64, sigma1: f64, sigma2: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma1 } else { sigma2 };
+        f64::exp(-0.5 * t * t)
+    };
+
+    Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Linear RGB of a flat spectrum of 1 over the sampled range, before white balancing.
+fn white_rgb() -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as usize;
+        let xyz = (0..steps).fold(Vec3::default(), |xyz, i| {
+            xyz + cie_xyz(LAMBDA_MIN + i as f64 + 0.5)
+        });
+        xyz_to_rgb(xyz)
+    })
+}
+
+/// Smits' (1999) conversion of an RGB color into a smooth spectrum, evaluated at `lambda`.
+fn smits(rgb: Color, lambda: f64) -> f64 {
+    const WHITE: [f64; 10] = [
+        1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+    ];
+    const CYAN: [f64; 10] = [
+        0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+    ];
+    const MAGENTA: [f64; 10] = [
+        1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+    ];
+    const YELLOW: [f64; 10] = [
+        0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+    ];
+    const RED: [f64; 10] = [
+        0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+    ];
+    const GREEN: [f64; 10] = [
+        0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+    ];
+    const BLUE: [f64; 10] = [
+        1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+    ];
+
+    // Interpolate linearly between the bin centers.
+    let x = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN) * 10.0 - 0.5;
+    let i = (x.floor() as isize).clamp(0, 8) as usize;
+    let t = (x - i as f64).clamp(0.0, 1.0);
+    let at = |table: &[f64; 10]| table[i] + (table[i + 1] - table[i]) * t;
+
+    let (r, g, b) = (rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0));
+    if r <= g && r <= b {
+        r * at(&WHITE)
+            + if g <= b {
+                (g - r) * at(&CYAN) + (b - g) * at(&BLUE)
+            } else {
+                (b - r) * at(&CYAN) + (g - b) * at(&GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * at(&WHITE)
+            + if r <= b {
+                (r - g) * at(&MAGENTA) + (b - r) * at(&BLUE)
+            } else {
+                (b - g) * at(&MAGENTA) + (r - b) * at(&RED)
+            }
+    } else {
+        b * at(&WHITE)
+            + if r <= g {
+                (r - b) * at(&YELLOW) + (g - r) * at(&GREEN)
+            } else {
+                (g - b) * at(&YELLOW) + (r - g) * at(&RED)
+            }
+    }
+}
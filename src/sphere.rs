@@ -1,29 +1,54 @@
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     material::Material,
     prelude::*,
+    stats,
 };
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Sphere {
-    center: Point3,
+    /// Center at time 0, moving by the ray direction every unit of time
+    center: Ray,
     radius: f64,
     mat: Rc<dyn Material>,
 }
 
 impl Sphere {
     pub fn new(center: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+        Self::new_moving(center, center, radius, mat)
+    }
+
+    /// Creates a sphere moving linearly from `center1` at time 0 to `center2` at time 1.
+    pub fn new_moving(
+        center1: Point3,
+        center2: Point3,
+        radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Self {
         Self {
-            center,
+            center: Ray::new(center1, center2 - center1),
             radius: f64::max(0.0, radius),
             mat,
         }
     }
+
+    /// Returns the surface coordinates of the point `p` on the unit sphere: `u` going around
+    /// the y axis from -x, and `v` from the bottom to the top.
+    fn sphere_uv(p: Point3) -> (f64, f64) {
+        let theta = f64::acos(-p.y());
+        let phi = f64::atan2(-p.z(), p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: Ray, ray_t: Interval) -> Option<HitRecord> {
-        let oc = self.center - r.origin();
+        stats::INTERSECTION_TESTS.inc();
+
+        let current_center = self.center.at(r.time());
+        let oc = current_center - r.origin();
         let a = r.direction().length_squared();
         let h = dot(r.direction(), oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -52,9 +77,19 @@ impl Hittable for Sphere {
             mat: self.mat.clone(),
             ..Default::default()
         };
-        let outward_normal = (p - self.center) / self.radius;
+        let outward_normal = (p - current_center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = Self::sphere_uv(outward_normal);
 
         Some(rec)
     }
+
+    /// Encloses the sphere over its motion from time 0 to 1.
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        let box0 = Aabb::from_points(self.center.at(0.0) - rvec, self.center.at(0.0) + rvec);
+        let box1 = Aabb::from_points(self.center.at(1.0) - rvec, self.center.at(1.0) + rvec);
+
+        Aabb::surrounding(&box0, &box1)
+    }
 }
@@ -0,0 +1,274 @@
+use std::{
+    io::{IsTerminal, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Process-wide event counter.
+#[derive(Debug)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    #[inline]
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Primary rays leaving the camera, one per pixel sample
+pub static CAMERA_RAYS: Counter = Counter::new();
+/// Rays scattered off a surface
+pub static BOUNCE_RAYS: Counter = Counter::new();
+/// Visibility rays cast towards a light
+pub static SHADOW_RAYS: Counter = Counter::new();
+/// Ray-primitive intersection tests
+pub static INTERSECTION_TESTS: Counter = Counter::new();
+
+static PHASES: Mutex<Vec<(String, Duration)>> = Mutex::new(Vec::new());
+
+/// Zeroes every counter, so that they count only the work of the render about to start.
+pub fn reset_counters() {
+    for counter in [
+        &CAMERA_RAYS,
+        &BOUNCE_RAYS,
+        &SHADOW_RAYS,
+        &INTERSECTION_TESTS,
+    ] {
+        counter.reset();
+    }
+}
+
+/// Forgets the phases timed so far, once they have been reported.
+pub fn clear_phases() {
+    PHASES.lock().expect("stats lock poisoned").clear();
+}
+
+/// Runs `f` and records how long it took under `name` (e.g. "scene build", "render").
+pub fn time_phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record_phase(name, start.elapsed());
+
+    result
+}
+
+pub fn record_phase(name: &str, duration: Duration) {
+    PHASES
+        .lock()
+        .expect("stats lock poisoned")
+        .push((name.to_owned(), duration));
+}
+
+/// Snapshot of all statistics gathered so far.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub camera_rays: u64,
+    pub bounce_rays: u64,
+    pub shadow_rays: u64,
+    pub intersection_tests: u64,
+    pub phases: Vec<(String, Duration)>,
+}
+
+impl Stats {
+    pub fn snapshot() -> Self {
+        Self {
+            camera_rays: CAMERA_RAYS.get(),
+            bounce_rays: BOUNCE_RAYS.get(),
+            shadow_rays: SHADOW_RAYS.get(),
+            intersection_tests: INTERSECTION_TESTS.get(),
+            phases: PHASES.lock().expect("stats lock poisoned").clone(),
+        }
+    }
+
+    /// Average number of segments per camera path.
+    pub fn average_path_length(&self) -> f64 {
+        if self.camera_rays == 0 {
+            0.0
+        } else {
+            (self.camera_rays + self.bounce_rays) as f64 / self.camera_rays as f64
+        }
+    }
+
+    /// Pixel samples per second spent in the "render" phases.
+    pub fn samples_per_second(&self) -> f64 {
+        let render_time: f64 = self
+            .phases
+            .iter()
+            .filter(|(name, _)| name == "render")
+            .map(|(_, duration)| duration.as_secs_f64())
+            .sum();
+        if render_time > 0.0 {
+            self.camera_rays as f64 / render_time
+        } else {
+            0.0
+        }
+    }
+
+    pub fn write_json(&self, mut out: impl Write) -> std::io::Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"rays\": {{")?;
+        writeln!(out, "    \"camera\": {},", self.camera_rays)?;
+        writeln!(out, "    \"bounce\": {},", self.bounce_rays)?;
+        writeln!(out, "    \"shadow\": {}", self.shadow_rays)?;
+        writeln!(out, "  }},")?;
+        writeln!(
+            out,
+            "  \"intersection_tests\": {},",
+            self.intersection_tests
+        )?;
+        writeln!(
+            out,
+            "  \"average_path_length\": {},",
+            self.average_path_length()
+        )?;
+        writeln!(
+            out,
+            "  \"samples_per_second\": {},",
+            self.samples_per_second()
+        )?;
+        writeln!(out, "  \"phases\": [")?;
+        for (index, (name, duration)) in self.phases.iter().enumerate() {
+            let separator = if index + 1 < self.phases.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "    {{ \"name\": \"{}\", \"seconds\": {} }}{separator}",
+                json_escape(name),
+                duration.as_secs_f64()
+            )?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+}
+
+/// Progress bar with an estimated time of arrival, drawn on stderr when it is a terminal.
+#[derive(Debug)]
+pub struct ProgressBar {
+    total: u64,
+    /// Work already done when the bar was created, as when resuming a render
+    initial: u64,
+    start: Instant,
+    last_draw: Option<Instant>,
+    enabled: bool,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 40;
+
+    pub fn new(total: u64) -> Self {
+        Self {
+            total: total.max(1),
+            initial: 0,
+            start: Instant::now(),
+            last_draw: None,
+            enabled: std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Starts the bar at `initial` units of work done before it was created, which the ETA does
+    /// not count as done in the elapsed time.
+    pub fn with_initial(mut self, initial: u64) -> Self {
+        self.initial = initial.min(self.total);
+
+        self
+    }
+
+    /// Updates the bar to `done` units of work, redrawing at most ten times per second.
+    pub fn set(&mut self, done: u64) {
+        if !self.enabled
+            || self
+                .last_draw
+                .is_some_and(|last_draw| last_draw.elapsed() < Duration::from_millis(100))
+        {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        self.draw(done.min(self.total));
+    }
+
+    /// Draws the completed bar and moves to the next line.
+    pub fn finish(&mut self) {
+        if self.enabled {
+            self.draw(self.total);
+            eprintln!();
+        }
+    }
+
+    fn draw(&self, done: u64) {
+        let fraction = done as f64 / self.total as f64;
+        let filled = (fraction * Self::WIDTH as f64) as usize;
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let eta = if done > self.initial {
+            format_duration(elapsed * (self.total - done) as f64 / (done - self.initial) as f64)
+        } else {
+            "--:--:--".to_owned()
+        };
+
+        eprint!(
+            "\r[{}{}] {:5.1}% elapsed {} ETA {}",
+            "#".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            100.0 * fraction,
+            format_duration(elapsed),
+            eta
+        );
+    }
+}
+
+/// Escapes `s` for use inside a JSON string: quotes, backslashes and control characters.
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
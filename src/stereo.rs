@@ -0,0 +1,117 @@
+use std::path::{Path, PathBuf};
+
+use crate::image::Image;
+
+/// Stereo rig settings. With a perspective projection the eyes look through a shared image
+/// plane at `convergence_distance` (off-axis stereo), with an equirectangular projection the
+/// result is an omnidirectional stereo (ODS) panorama, and other projections use two parallel
+/// cameras.
+#[derive(Debug, Clone)]
+pub struct Stereo {
+    /// Distance between the two eyes, in scene units
+    pub interpupillary_distance: f64,
+    /// Distance from the camera at which both eyes see objects at the same image position
+    pub convergence_distance: f64,
+    pub layout: StereoLayout,
+}
+
+impl Default for Stereo {
+    fn default() -> Self {
+        Self {
+            interpupillary_distance: 0.064,
+            convergence_distance: 10.0,
+            layout: StereoLayout::SideBySide,
+        }
+    }
+}
+
+impl Stereo {
+    pub fn with_interpupillary_distance(mut self, interpupillary_distance: f64) -> Self {
+        self.interpupillary_distance = interpupillary_distance;
+
+        self
+    }
+
+    pub fn with_convergence_distance(mut self, convergence_distance: f64) -> Self {
+        self.convergence_distance = convergence_distance;
+
+        self
+    }
+
+    pub fn with_layout(mut self, layout: StereoLayout) -> Self {
+        self.layout = layout;
+
+        self
+    }
+}
+
+/// How the two eyes are output.
+#[derive(Debug, Clone)]
+pub enum StereoLayout {
+    /// One image twice as wide, left eye on the left
+    SideBySide,
+    /// One image twice as tall, left eye on top
+    TopBottom,
+    /// Each eye written to its own file instead of standard output
+    SeparateFiles { left: PathBuf, right: PathBuf },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    /// Direction of the eye from the rig center, along the camera's right vector.
+    pub fn sign(&self) -> f64 {
+        match self {
+            Eye::Left => -1.0,
+            Eye::Right => 1.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Eye::Left => "left",
+            Eye::Right => "right",
+        }
+    }
+
+    /// Returns `path` with the eye name appended to the file stem, e.g. `render_left.ckpt`.
+    pub fn suffixed(&self, path: &Path) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut file_name = format!("{stem}_{}", self.name());
+        if let Some(extension) = path.extension() {
+            file_name = format!("{file_name}.{}", extension.to_string_lossy());
+        }
+
+        path.with_file_name(file_name)
+    }
+}
+
+/// Packs a left and a right image of the same size next to each other.
+pub fn side_by_side(left: &Image, right: &Image) -> Image {
+    let mut image = Image::new(2 * left.width, left.height);
+    for j in 0..left.height {
+        for i in 0..left.width {
+            image.set(i, j, left.get(i, j));
+            image.set(left.width + i, j, right.get(i, j));
+        }
+    }
+
+    image
+}
+
+/// Packs a left and a right image of the same size above each other.
+pub fn top_bottom(left: &Image, right: &Image) -> Image {
+    let mut image = Image::new(left.width, 2 * left.height);
+    for j in 0..left.height {
+        for i in 0..left.width {
+            image.set(i, j, left.get(i, j));
+            image.set(i, left.height + j, right.get(i, j));
+        }
+    }
+
+    image
+}
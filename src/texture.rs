@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use crate::{image::Image, prelude::*};
+
+/// Color that varies over a surface, looked up by surface coordinates `u`, `v` in `[0, 1]`
+/// and the world position `p`.
+pub trait Texture: std::fmt::Debug {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Alternating cubes of two textures filling space.
+#[derive(Debug, Clone)]
+pub struct CheckerTexture {
+    /// Number of cubes per unit length
+    inv_scale: f64,
+    even: Rc<dyn Texture>,
+    odd: Rc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Rc<dyn Texture>, odd: Rc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        Self::new(
+            scale,
+            Rc::new(SolidColor::new(even)),
+            Rc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let x = f64::floor(self.inv_scale * p.x()) as i64;
+        let y = f64::floor(self.inv_scale * p.y()) as i64;
+        let z = f64::floor(self.inv_scale * p.z()) as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Image mapped onto the surface coordinates, with `v` = 1 at the top row.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Self {
+        Self { image }
+    }
+
+    /// Loads a `.ppm`, a Radiance `.hdr` or a `.pfm` image. PPM values are used as stored.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let input = std::io::BufReader::new(std::fs::File::open(path)?);
+        let extension = path.extension().unwrap_or_default().to_ascii_lowercase();
+        let image = match extension.to_str() {
+            Some("ppm") => Image::read_ppm(input)?,
+            Some("hdr") | Some("pic") => Image::read_hdr(input)?,
+            Some("pfm") => Image::read_pfm(input)?,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "image textures must be .ppm, .hdr or .pfm files",
+                ));
+            }
+        };
+
+        Ok(Self::new(image))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        if self.image.width <= 0 || self.image.height <= 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let i = ((u * self.image.width as f64) as i32).min(self.image.width - 1);
+        let j = ((v * self.image.height as f64) as i32).min(self.image.height - 1);
+
+        self.image.get(i, j)
+    }
+}
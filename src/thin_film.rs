@@ -0,0 +1,188 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{hittable::HitRecord, prelude::*, texture::Texture};
+
+/// Transparent coating thin enough for the light reflected off its two sides to interfere,
+/// coloring the reflection by thickness and viewing angle like a soap bubble or oil slick.
+#[derive(Debug, Clone)]
+pub struct ThinFilm {
+    /// Film thickness in nanometers
+    pub thickness: f64,
+    /// Refractive index of the film
+    pub ior: f64,
+    /// Texture whose luminance scales `thickness` over the surface, or `None` for an even film
+    pub thickness_map: Option<Rc<dyn Texture>>,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        Self {
+            thickness,
+            ior,
+            thickness_map: None,
+        }
+    }
+
+    pub fn with_thickness_map(mut self, thickness_map: Rc<dyn Texture>) -> Self {
+        self.thickness_map = Some(thickness_map);
+
+        self
+    }
+
+    /// Film thickness in nanometers at `rec`.
+    pub fn thickness_at(&self, rec: &HitRecord) -> f64 {
+        match &self.thickness_map {
+            Some(map) => self.thickness * luminance(map.value(rec.u, rec.v, rec.p)).max(0.0),
+            None => self.thickness,
+        }
+    }
+
+    /// Unpolarized reflectance at `lambda` nanometers of a film `thickness` nanometers thick
+    /// lying between a medium of index `eta_incident`, which the light arrives from at
+    /// `cos_theta` to the normal, and a base of complex index `eta_base` + i`k_base`.
+    pub fn reflectance(
+        &self,
+        thickness: f64,
+        cos_theta: f64,
+        eta_incident: f64,
+        eta_base: f64,
+        k_base: f64,
+        lambda: f64,
+    ) -> f64 {
+        let n1 = Complex::real(eta_incident);
+        let n2 = Complex::real(self.ior);
+        let n3 = Complex::new(eta_base, k_base);
+
+        // Each medium's index times the cosine of the angle in it, from Snell's law. Complex
+        // values cover total internal reflection and absorbing bases.
+        let sin2_n1 = Complex::real((1.0 - cos_theta * cos_theta) * eta_incident * eta_incident);
+        let q1 = Complex::real(eta_incident * cos_theta);
+        let q2 = (n2 * n2 - sin2_n1).sqrt();
+        let q3 = (n3 * n3 - sin2_n1).sqrt();
+
+        // Phase difference of one round trip through the film.
+        let delta = Complex::real(4.0 * PI * thickness / lambda) * q2;
+        let phase = (Complex::new(0.0, 1.0) * delta).exp();
+
+        let airy = |r12: Complex, r23: Complex| {
+            let r = (r12 + r23 * phase) / (Complex::real(1.0) + r12 * r23 * phase);
+            r.norm_sqr()
+        };
+        let rs = airy(fresnel_s(q1, q2), fresnel_s(q2, q3));
+        let rp = airy(fresnel_p(n1, q1, n2, q2), fresnel_p(n2, q2, n3, q3));
+
+        (0.5 * (rs + rp)).clamp(0.0, 1.0)
+    }
+
+    /// Reflectance of the film at `rec` at each of `lambda`, given the indices of the incident
+    /// medium and of the base at each wavelength.
+    pub fn reflectances(
+        &self,
+        rec: &HitRecord,
+        cos_theta: f64,
+        lambda: [f64; 3],
+        eta_incident: Vec3,
+        eta_base: Vec3,
+        k_base: Vec3,
+    ) -> Color {
+        let thickness = self.thickness_at(rec);
+        Color {
+            e: std::array::from_fn(|i| {
+                self.reflectance(
+                    thickness,
+                    cos_theta,
+                    eta_incident[i],
+                    eta_base[i],
+                    k_base[i],
+                    lambda[i],
+                )
+            }),
+        }
+    }
+}
+
+/// Amplitude reflection coefficient for light polarized perpendicular to the plane of
+/// incidence, from the index times the cosine of the angle on each side.
+fn fresnel_s(q_i: Complex, q_t: Complex) -> Complex {
+    (q_i - q_t) / (q_i + q_t)
+}
+
+/// Amplitude reflection coefficient for light polarized parallel to the plane of incidence,
+/// from the index and the index times the cosine of the angle on each side.
+fn fresnel_p(n_i: Complex, q_i: Complex, n_t: Complex, q_t: Complex) -> Complex {
+    let a = n_t * n_t * q_i;
+    let b = n_i * n_i * q_t;
+    (a - b) / (a + b)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn real(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Principal square root, with a non-negative real part.
+    fn sqrt(self) -> Self {
+        let norm = f64::hypot(self.re, self.im);
+        let re = f64::sqrt(0.5 * (norm + self.re).max(0.0));
+        let im = f64::sqrt(0.5 * (norm - self.re).max(0.0));
+        Self::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn exp(self) -> Self {
+        let scale = f64::exp(self.re);
+        Self::new(scale * f64::cos(self.im), scale * f64::sin(self.im))
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let denominator = rhs.norm_sqr();
+        Self::new(
+            (self.re * rhs.re + self.im * rhs.im) / denominator,
+            (self.im * rhs.re - self.re * rhs.im) / denominator,
+        )
+    }
+}
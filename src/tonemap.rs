@@ -0,0 +1,206 @@
+use crate::prelude::*;
+
+/// Operator compressing scene-referred linear values into the displayable `[0, 1]` range.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum ToneMap {
+    /// Clip every component at 1
+    #[default]
+    Clamp,
+    /// Extended Reinhard on luminance, mapping `white` (and anything brighter) to 1
+    Reinhard { white: f64 },
+    /// John Hable's filmic curve from Uncharted 2
+    Hable,
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output transforms
+    Aces,
+    /// Troy Sobotka's AgX, using the polynomial approximation of its base contrast curve
+    AgX,
+}
+
+/// Encoding from linear display values to the values stored in the output image.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum TransferFunction {
+    /// Gamma 2, i.e. a square root
+    #[default]
+    Gamma2,
+    /// IEC 61966-2-1 sRGB
+    Srgb,
+    /// ITU-R BT.709 camera transfer function
+    Rec709,
+}
+
+/// Conversion of the linear render into low dynamic range output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ToneMapping {
+    /// Exposure adjustment in stops; each stop doubles the brightness
+    pub exposure: f64,
+    pub operator: ToneMap,
+    pub transfer: TransferFunction,
+}
+
+impl ToneMapping {
+    pub fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+
+        self
+    }
+
+    pub fn with_operator(mut self, operator: ToneMap) -> Self {
+        self.operator = operator;
+
+        self
+    }
+
+    pub fn with_transfer(mut self, transfer: TransferFunction) -> Self {
+        self.transfer = transfer;
+
+        self
+    }
+
+    /// Returns the encoded display color, with components in `[0, 1]`, for `linear_color`.
+    pub fn apply(&self, linear_color: Color) -> Color {
+        let exposed = f64::powf(2.0, self.exposure) * linear_color;
+        let exposed = Color::new(
+            f64::max(exposed.x(), 0.0),
+            f64::max(exposed.y(), 0.0),
+            f64::max(exposed.z(), 0.0),
+        );
+
+        let mapped = match self.operator {
+            ToneMap::Clamp => exposed,
+            ToneMap::Reinhard { white } => reinhard_extended(exposed, white),
+            ToneMap::Hable => hable(exposed),
+            ToneMap::Aces => aces_fitted(exposed),
+            ToneMap::AgX => agx(exposed),
+        };
+
+        let display = Interval::new(0.0, 1.0);
+        Color {
+            e: mapped.e.map(|c| self.transfer.encode(display.clamp(c))),
+        }
+    }
+}
+
+impl TransferFunction {
+    #[inline]
+    pub fn encode(&self, linear: f64) -> f64 {
+        match self {
+            Self::Gamma2 => linear_to_gamma(linear),
+            Self::Srgb => {
+                if linear <= 0.0031308 {
+                    12.92 * linear
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Self::Rec709 => {
+                if linear < 0.018 {
+                    4.5 * linear
+                } else {
+                    1.099 * linear.powf(0.45) - 0.099
+                }
+            }
+        }
+    }
+}
+
+fn reinhard_extended(color: Color, white: f64) -> Color {
+    let l = luminance(color);
+    if l <= 0.0 {
+        return color;
+    }
+    let mapped = l * (1.0 + l / (white * white)) / (1.0 + l);
+
+    (mapped / l) * color
+}
+
+fn hable(color: Color) -> Color {
+    fn partial(x: f64) -> f64 {
+        const A: f64 = 0.15;
+        const B: f64 = 0.50;
+        const C: f64 = 0.10;
+        const D: f64 = 0.20;
+        const E: f64 = 0.02;
+        const F: f64 = 0.30;
+        ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+    }
+
+    const EXPOSURE_BIAS: f64 = 2.0;
+    const WHITE_POINT: f64 = 11.2;
+    let white_scale = 1.0 / partial(WHITE_POINT);
+
+    Color {
+        e: color.e.map(|c| partial(EXPOSURE_BIAS * c) * white_scale),
+    }
+}
+
+fn aces_fitted(color: Color) -> Color {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let rrt_and_odt_fit = |v: f64| {
+        let a = v * (v + 0.0245786) - 0.000090537;
+        let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+        a / b
+    };
+
+    let color = mat_mul(&INPUT, color);
+    let color = Color {
+        e: color.e.map(rrt_and_odt_fit),
+    };
+
+    mat_mul(&OUTPUT, color)
+}
+
+fn agx(color: Color) -> Color {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let contrast = |x: f64| {
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    };
+
+    let color = mat_mul(&INSET, color);
+    let color = Color {
+        e: color.e.map(|c| {
+            let log = f64::log2(f64::max(c, 1e-10)).clamp(MIN_EV, MAX_EV);
+            contrast((log - MIN_EV) / (MAX_EV - MIN_EV))
+        }),
+    };
+    let color = mat_mul(&OUTSET, color);
+
+    // The curve produces display-encoded values; return to linear for the transfer function.
+    Color {
+        e: color.e.map(|c| f64::max(c, 0.0).powf(2.2)),
+    }
+}
+
+fn mat_mul(m: &[[f64; 3]; 3], v: Color) -> Color {
+    Color::new(
+        m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+        m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+        m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+    )
+}
@@ -3,6 +3,8 @@ use std::{
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub},
 };
 
+use crate::rng;
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Vec3 {
     pub e: [f64; 3],
@@ -42,14 +44,14 @@ impl Vec3 {
     }
 
     pub fn random() -> Self {
-        Vec3 { e: rand::random() }
+        Vec3 { e: rng::random() }
     }
 
     pub fn random_range(min: f64, max: f64) -> Self {
         Vec3::new(
-            rand::random_range(min..max),
-            rand::random_range(min..max),
-            rand::random_range(min..max),
+            rng::random_range(min..max),
+            rng::random_range(min..max),
+            rng::random_range(min..max),
         )
     }
 }
@@ -232,8 +234,8 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
 pub fn random_in_unit_disk() -> Vec3 {
     loop {
         let p = Vec3::new(
-            rand::random_range(-1.0..1.0),
-            rand::random_range(-1.0..1.0),
+            rng::random_range(-1.0..1.0),
+            rng::random_range(-1.0..1.0),
             0.0,
         );
         if p.length_squared() < 1.0 {